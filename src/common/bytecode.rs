@@ -25,17 +25,22 @@ pub enum Type {
     Unit    = 0x00,
     Bool    = 0x01,
     Int     = 0x02,
+    Char    = 0x03,
     Sym     = 0x05,
     Fun     = 0x08,
-    Prim    = 0x09
+    Prim    = 0x09,
+    Const   = 0x0A
 }
 
+// a pc is the index of the module in the high 32 bits,
+// and the offset in its text in the low 32 bits
+
 #[inline(always)]
 pub fn base(pc: u64) -> u32 {
-    ((pc & 0xFFFF0000) >> 32) as u32
+    (pc >> 32) as u32
 }
 
 #[inline(always)]
 pub fn off(pc: u64) -> u32 {
-    (pc & 0xFFFF) as u32
+    (pc & 0xFFFFFFFF) as u32
 }
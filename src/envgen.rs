@@ -1,49 +1,524 @@
-#[allow(dead_code)];
+#![allow(dead_code)]
+#![feature(slice_patterns)]
+#![feature(const_fn)]
 
-extern mod gmp;
+// Generates the table of the primitives of the VM for the compiler,
+// or checks that the table used by the compiler is up to date
+
+extern crate gmp;
+extern crate libc;
+
+#[macro_use]
+extern crate log;
 
 mod common;
+#[macro_use]
 mod gc;
 mod primitives;
 mod vm;
 
-fn gen_primitives_env(out: &mut Writer) {
-    let mut vm = vm::VM::new();
-    let penv = primitives::env(vm.gc);
-    let vals = unsafe { &(*penv).values };
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::process;
+
+macro_rules! try_opt {
+    ($e:expr) => (match $e { Some(v) => v, None => return None })
+}
+
+static USAGE: &'static str = "json | haskell | check <file>";
+
+fn json_string(s: &str) -> String {
+    let mut ret = String::from("\"");
+
+    for c in s.chars() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            c if (c as u32) < 0x20 => ret.push_str(&format!("\\u{:04x}", c as u32)),
+            c => ret.push(c)
+        }
+    }
+
+    ret.push('"');
+    ret
+}
+
+// a Haskell string literal, non-ASCII and control characters are
+// escaped by their decimal code
+fn haskell_string(s: &str) -> String {
+    let mut ret = String::from("\"");
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            c if c < ' ' || c > '~' => {
+                ret.push_str(&format!("\\{}", c as u32));
+
+                // \& ends the escape if a digit follows
+                match chars.peek() {
+                    Some(c) if c.is_digit(10) => ret.push_str("\\&"),
+                    _ => ()
+                }
+            }
+
+            c => ret.push(c)
+        }
+    }
+
+    ret.push('"');
+    ret
+}
+
+fn gen_json(out: &mut Write) -> io::Result<()> {
+    try!(writeln!(out, "["));
+
+    for (i, d) in primitives::TABLE.iter().enumerate() {
+        let sep = if i + 1 < primitives::TABLE.len() { "," } else { "" };
+        try!(writeln!(out, "  {{ \"name\": {}, \"index\": {}, \"arity\": {}, \
+                            \"variadic\": {} }}{}",
+                      json_string(d.name), i, d.arity, d.variadic, sep));
+    }
+
+    writeln!(out, "]")
+}
+
+fn gen_haskell(out: &mut Write) -> io::Result<()> {
+    let names: Vec<String> = primitives::TABLE.iter()
+        .map(|d| haskell_string(d.name)).collect();
+
+    try!(writeln!(out, "module Primitives"));
+    try!(writeln!(out, "("));
+    try!(writeln!(out, "    primEnv,"));
+    try!(writeln!(out, "    primInfo"));
+    try!(writeln!(out, ") where"));
+    try!(writeln!(out, ""));
+    try!(writeln!(out, "primEnv :: [String]"));
+    try!(writeln!(out, "primEnv = ["));
+    try!(writeln!(out, "    {}", names.join(",\n    ")));
+    try!(writeln!(out, "    ]"));
+    try!(writeln!(out, ""));
+    try!(writeln!(out, "-- name, arity, and whether more arguments are accepted"));
+    try!(writeln!(out, "primInfo :: [(String, Int, Bool)]"));
+    try!(writeln!(out, "primInfo = ["));
+
+    for (i, d) in primitives::TABLE.iter().enumerate() {
+        let sep = if i + 1 < primitives::TABLE.len() { "," } else { "" };
+        try!(writeln!(out, "    ({}, {}, {}){}", names[i], d.arity,
+                      if d.variadic { "True" } else { "False" }, sep));
+    }
+
+    writeln!(out, "    ]")
+}
+
+// a primitive of a table: its name, and its arity and variadic flag
+// if the table has them
+type Entry = (String, Option<(u8, bool)>);
+
+// a token of a generated table: a string literal, still escaped, a
+// word such as a number or a boolean, or a punctuation character
+enum Token {
+    Str(String),
+    Word(String),
+    Punct(char)
+}
+
+fn tokens(s: &str) -> Vec<Token> {
+    let mut ret = vec!();
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => (),
+            // Haskell comments, up to the end of the line
+            '-' if chars.peek() == Some(&'-') => {
+                while let Some(c) = chars.next() {
+                    if c == '\n' {
+                        break
+                    }
+                }
+            }
+
+            '"' => {
+                let mut lit = String::new();
+
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => {
+                            lit.push(c);
+                            match chars.next() {
+                                Some(c) => lit.push(c),
+                                None => break
+                            }
+                        }
+
+                        c => lit.push(c)
+                    }
+                }
+
+                ret.push(Token::Str(lit));
+            }
+
+            '[' | ']' | '(' | ')' | '{' | '}' | ',' | ':' => ret.push(Token::Punct(c)),
+            c => {
+                let mut word = c.to_string();
+
+                loop {
+                    match chars.peek() {
+                        Some(&c) if c.is_alphanumeric() || c == '_' || c == '-' => word.push(c),
+                        _ => break
+                    }
+
+                    chars.next();
+                }
+
+                ret.push(Token::Word(word));
+            }
+        }
+    }
+
+    ret
+}
+
+fn json_unescape(lit: &str) -> Option<String> {
+    let mut ret = String::new();
+    let mut chars = lit.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            ret.push(c);
+            continue
+        }
+
+        ret.push(match chars.next() {
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                match u32::from_str_radix(&hex, 16).ok().and_then(::std::char::from_u32) {
+                    Some(c) if hex.len() == 4 => c,
+                    _ => return None
+                }
+            }
+
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some(c) => c,
+            None => return None
+        });
+    }
+
+    Some(ret)
+}
+
+fn haskell_unescape(lit: &str) -> Option<String> {
+    let mut ret = String::new();
+    let mut chars = lit.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            ret.push(c);
+            continue
+        }
+
+        match chars.next() {
+            // \& separates a numeric escape from a following digit
+            Some('&') => (),
+            Some(c) if c.is_digit(10) => {
+                let mut code = c.to_digit(10).unwrap();
+
+                while let Some(d) = chars.peek().and_then(|c| c.to_digit(10)) {
+                    code = match code.checked_mul(10).and_then(|n| n.checked_add(d)) {
+                        Some(n) => n,
+                        None => return None
+                    };
+
+                    chars.next();
+                }
+
+                match ::std::char::from_u32(code) {
+                    Some(c) => ret.push(c),
+                    None => return None
+                }
+            }
+
+            Some('n') => ret.push('\n'),
+            Some('t') => ret.push('\t'),
+            Some('r') => ret.push('\r'),
+            Some(c) => ret.push(c),
+            None => return None
+        }
+    }
+
+    Some(ret)
+}
+
+// the text following the definition of the given Haskell value, which
+// starts a line, as in "primEnv = ["
+fn definition<'a>(table: &'a str, name: &str) -> Option<&'a str> {
+    let mut start = 0;
+
+    for line in table.split('\n') {
+        if line.starts_with(name) {
+            let rest = line[name.len() ..].trim_left();
+            if rest.starts_with('=') && !rest.starts_with("==") {
+                return Some(&table[start + line.len() - rest.len() + 1 ..])
+            }
+        }
+
+        start += line.len() + 1;
+    }
+
+    None
+}
+
+// the names of a Haskell list of strings, such as primEnv
+fn haskell_names(list: &str) -> Option<Vec<Entry>> {
+    let toks = tokens(list);
+    let mut toks = toks.iter();
+    let mut entries = vec!();
+
+    match toks.next() {
+        Some(&Token::Punct('[')) => (),
+        _ => return None
+    }
+
+    loop {
+        match toks.next() {
+            Some(&Token::Punct(']')) => break,
+            Some(&Token::Punct(',')) => (),
+            Some(&Token::Str(ref name)) => {
+                entries.push((try_opt!(haskell_unescape(name)), None))
+            }
+
+            _ => return None
+        }
+    }
 
-    writeln!(out, "module Primitives");
-    writeln!(out, "(");
-    writeln!(out, "    primEnv");
-    writeln!(out, ") where");
-    writeln!(out, "");
-    writeln!(out, "primEnv :: [String]");
-    writeln!(out, "primEnv = [");
-    let mut it = vals.iter();
+    Some(entries)
+}
 
-    // first element: don't print the comma before
-    match it.next() {
-        Some(&(_, gc::value::Primitive(_, name))) =>
-            write!(out, "    \"{:s}\"", name),
+// the entries of a Haskell list of (name, arity, variadic), such as primInfo
+fn haskell_info(list: &str) -> Option<Vec<Entry>> {
+    let toks = tokens(list);
+    let mut toks = toks.iter();
+    let mut entries = vec!();
 
-        Some(_) => (),
-        None => return
+    match toks.next() {
+        Some(&Token::Punct('[')) => (),
+        _ => return None
     }
 
-    for i in it {
-        match i {
-            &(_, gc::value::Primitive(_, name)) => {
-                write!(out, ",\n");
-                write!(out, "    \"{:s}\"", name);
+    loop {
+        match toks.next() {
+            Some(&Token::Punct(']')) => break,
+            Some(&Token::Punct(',')) => continue,
+            Some(&Token::Punct('(')) => (),
+            _ => return None
+        }
+
+        let tuple: Vec<&Token> = toks.by_ref().take(6).collect();
+        match &tuple[..] {
+            [&Token::Str(ref name), &Token::Punct(','), &Token::Word(ref arity),
+             &Token::Punct(','), &Token::Word(ref variadic), &Token::Punct(')')] => {
+                let name = try_opt!(haskell_unescape(name));
+                let arity = try_opt!(arity.parse().ok());
+                let variadic = match &variadic[..] {
+                    "True" => true,
+                    "False" => false,
+                    _ => return None
+                };
+
+                entries.push((name, Some((arity, variadic))));
             }
 
-            _ => ()
+            _ => return None
         }
     }
 
-    writeln!(out, "\n    ]");
+    Some(entries)
+}
+
+// the entries of a JSON table,
+// [{ "name": .., "index": .., "arity": .., "variadic": .. }, ..]
+fn json_entries(table: &str) -> Option<Vec<Entry>> {
+    let toks = tokens(table);
+    let mut toks = toks.iter();
+    let mut entries = vec!();
+
+    match toks.next() {
+        Some(&Token::Punct('[')) => (),
+        _ => return None
+    }
+
+    loop {
+        match toks.next() {
+            Some(&Token::Punct(']')) => break,
+            Some(&Token::Punct(',')) => continue,
+            Some(&Token::Punct('{')) => (),
+            _ => return None
+        }
+
+        let (mut name, mut arity, mut variadic) = (None, None, None);
+
+        loop {
+            match (toks.next(), toks.next(), toks.next()) {
+                (Some(&Token::Str(ref key)), Some(&Token::Punct(':')), Some(v)) => {
+                    match (&key[..], v) {
+                        ("name", &Token::Str(ref n)) => name = json_unescape(n),
+                        ("arity", &Token::Word(ref a)) => arity = a.parse().ok(),
+                        ("variadic", &Token::Word(ref v)) if v == "true" => variadic = Some(true),
+                        ("variadic", &Token::Word(ref v)) if v == "false" => variadic = Some(false),
+                        ("name", _) | ("arity", _) | ("variadic", _) => return None,
+                        _ => ()
+                    }
+                }
+
+                _ => return None
+            }
+
+            match toks.next() {
+                Some(&Token::Punct(',')) => (),
+                Some(&Token::Punct('}')) => break,
+                _ => return None
+            }
+        }
+
+        match (name, arity, variadic) {
+            (Some(n), Some(a), Some(v)) => entries.push((n, Some((a, v)))),
+            _ => return None
+        }
+    }
+
+    Some(entries)
+}
+
+// the lists of primitives of a table, with their names for error
+// messages, empty for JSON tables. A Haskell module must define primEnv,
+// the list the compiler takes the indexes of the primitives from, and
+// may define primInfo
+fn tables(table: &str) -> Option<Vec<(&'static str, Vec<Entry>)>> {
+    match definition(table, "primEnv") {
+        Some(list) => {
+            let mut ret = vec!(("primEnv", try_opt!(haskell_names(list))));
+
+            match definition(table, "primInfo") {
+                Some(list) => ret.push(("primInfo", try_opt!(haskell_info(list)))),
+                None => ()
+            }
+
+            Some(ret)
+        }
+
+        None => Some(vec!(("", try_opt!(json_entries(table)))))
+    }
+}
+
+fn arity(arity: u8, variadic: bool) -> String {
+    format!("{}{}", arity, if variadic { "+" } else { "" })
+}
+
+fn check(path: &str) -> bool {
+    let mut err = io::stderr();
+    let mut table = String::new();
+
+    match fs::File::open(path).and_then(|mut f| f.read_to_string(&mut table)) {
+        Ok(_) => (),
+        Err(e) => {
+            let _ = writeln!(err, "cannot read {}: {}", path, e);
+            return false
+        }
+    }
+
+    let tables = match tables(&table) {
+        Some(tables) => tables,
+        None => {
+            let _ = writeln!(err, "{}: not a primitive table", path);
+            return false
+        }
+    };
+
+    let mut ok = true;
+
+    for &(name, ref entries) in tables.iter() {
+        let what = if name.is_empty() {
+            path.to_string()
+        } else {
+            format!("{}: {}", path, name)
+        };
+
+        ok &= check_entries(&what, entries);
+    }
+
+    ok
+}
+
+// compare a list of primitives to the table of the VM, arities are only
+// compared if the list has them
+fn check_entries(what: &str, entries: &[Entry]) -> bool {
+    let mut err = io::stderr();
+    let mut ok = true;
+
+    for (i, d) in primitives::TABLE.iter().enumerate() {
+        match entries.get(i) {
+            Some(&(ref n, _)) if n != d.name => {
+                let _ = writeln!(err, "{}: primitive {} is {}, expected {}",
+                                 what, i, n, d.name);
+                ok = false;
+            }
+
+            Some(&(_, Some((a, v)))) if a != d.arity || v != d.variadic => {
+                let _ = writeln!(err, "{}: primitive {} ({}) takes {} arguments, expected {}",
+                                 what, i, d.name, arity(a, v), arity(d.arity, d.variadic));
+                ok = false;
+            }
+
+            Some(_) => (),
+            None => {
+                let _ = writeln!(err, "{}: missing primitive {} ({})", what, i, d.name);
+                ok = false;
+            }
+        }
+    }
+
+    for (i, e) in entries.iter().enumerate().skip(primitives::TABLE.len()) {
+        let _ = writeln!(err, "{}: unknown primitive {} ({})", what, i, e.0);
+        ok = false;
+    }
+
+    ok
 }
 
 fn main() {
-    gen_primitives_env(&mut ::std::io::stdout() as &mut Writer);
+    let args: Vec<String> = ::std::env::args().collect();
+    let out = io::stdout();
+    let mut out = out.lock();
+
+    let res = match args.iter().map(|s| &s[..]).collect::<Vec<&str>>()[..] {
+        [_, "json"] => gen_json(&mut out),
+        [_, "haskell"] => gen_haskell(&mut out),
+        [_, "check", path] => {
+            if !check(path) {
+                process::exit(1);
+            }
+
+            Ok(())
+        }
+
+        _ => {
+            let _ = writeln!(io::stderr(), "usage: {} {}", args[0], USAGE);
+            process::exit(2);
+        }
+    };
+
+    match res {
+        Ok(()) => (),
+        Err(e) => {
+            let _ = writeln!(io::stderr(), "{}", e);
+            process::exit(1);
+        }
+    }
 }
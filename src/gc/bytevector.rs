@@ -0,0 +1,22 @@
+use std::fmt;
+use gc;
+
+// a garbage-collected Scheme bytevector
+
+#[repr(packed)]
+pub struct Bytevector {
+    pub bytes: Vec<u8>,
+    pub mutable: bool
+}
+
+impl gc::visit::Visitor for Bytevector {
+    fn visit(&mut self, _: bool) {
+    }
+}
+
+impl fmt::Display for Bytevector {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let bytes: Vec<String> = self.bytes.iter().map(|b| b.to_string()).collect();
+        fmt.pad(&bytes.join(" "))
+    }
+}
@@ -36,8 +36,9 @@ pub struct GC {
     // string interner
     // keeps in memory all the string constants loaded by the program.
     // They include notably string literals, but also symbol names
-    // Currently, the interned strings are managed by the GC although they are
-    // not currently collected. This may change in the future
+    // The interned strings are managed by the GC although they are
+    // not currently collected (the interner is a root). This may change
+    // in the future
     // all interned strings are immutable
     interner: HashMap<String, gc::Ptr<gc::String>>,
 
@@ -54,8 +55,16 @@ impl GC {
     }
 
     fn mark(&mut self, roots: &mut [&mut gc::visit::Visitor]) {
+        use gc::visit::Visitor;
+        let m = self.current_mark;
+
         for v in roots.iter_mut() {
-            v.visit(self.current_mark);
+            v.visit(m);
+        }
+
+        // interned strings are never collected
+        for s in self.interner.values_mut() {
+            s.visit(m);
         }
     }
 
@@ -120,6 +129,11 @@ impl GC {
         interned
     }
 
+    // all the interned strings, in no particular order
+    pub fn interned(&self) -> Vec<gc::Ptr<gc::String>> {
+        self.interner.values().map(|s| *s).collect()
+    }
+
     pub fn alloc<T: 'static>(&mut self, data: T) -> gc::Ptr<T> {
         use gc::ptr::Cell;
 
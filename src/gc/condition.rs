@@ -0,0 +1,38 @@
+use std::fmt;
+use gc;
+use vm::error::Backtrace;
+
+// a garbage-collected condition object, as raised by the VM on errors
+// the kind is a short tag such as "error" or "stack-overflow" that Scheme
+// handlers can dispatch on
+
+pub struct Condition {
+    pub kind: &'static str,
+    pub message: ::std::string::String,
+    pub irritants: gc::Value,
+    pub backtrace: Backtrace
+}
+
+impl gc::visit::Visitor for Condition {
+    fn visit(&mut self, m: bool) {
+        self.irritants.visit(m);
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self.irritants {
+            gc::value::Null => fmt.pad(
+                &format!("#<{}: {}>", self.kind, self.message)
+            ),
+
+            gc::value::Pair(p) => fmt.pad(
+                &format!("#<{}: {} {}>", self.kind, self.message, p)
+            ),
+
+            ref v => fmt.pad(
+                &format!("#<{}: {} {}>", self.kind, self.message, v)
+            )
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use gc;
+use gc::value;
+
+// a garbage-collected Scheme hash table
+// keys are hashed with Value::hash, by address unless the table compares
+// them structurally. Tables of eq? compare numbers like eqv?, since eq?
+// doesn't tell whether equal numbers are the same
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Equiv {
+    Eq = 0,
+    Eqv = 1,
+    Equal = 2,
+    String = 3
+}
+
+impl Equiv {
+    pub fn same(self, k1: &gc::Value, k2: &gc::Value) -> bool {
+        match self {
+            Equiv::Eq | Equiv::Eqv => k1.eqv(k2),
+            Equiv::Equal => k1.compare(k2),
+            Equiv::String => match (k1, k2) {
+                (&value::String(s1), &value::String(s2)) => s1.str == s2.str,
+                _ => false
+            }
+        }
+    }
+
+    pub fn hash(self, k: &gc::Value) -> u64 {
+        k.hash(self == Equiv::Equal || self == Equiv::String)
+    }
+}
+
+pub struct HashTable {
+    pub equiv: Equiv,
+    buckets: HashMap<u64, Vec<(gc::Value, gc::Value)>>,
+    count: usize
+}
+
+impl HashTable {
+    pub fn new(equiv: Equiv) -> HashTable {
+        HashTable { equiv: equiv, buckets: HashMap::new(), count: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn get(&self, k: &gc::Value) -> Option<gc::Value> {
+        let equiv = self.equiv;
+
+        match self.buckets.get(&equiv.hash(k)) {
+            Some(b) => b.iter().find(|e| equiv.same(&e.0, k)).map(|e| e.1.clone()),
+            None => None
+        }
+    }
+
+    pub fn insert(&mut self, k: &gc::Value, v: &gc::Value) {
+        let equiv = self.equiv;
+        let bucket = self.buckets.entry(equiv.hash(k)).or_insert(vec!());
+
+        for e in bucket.iter_mut() {
+            if equiv.same(&e.0, k) {
+                e.1 = v.clone();
+                return
+            }
+        }
+
+        bucket.push((k.clone(), v.clone()));
+        self.count += 1;
+    }
+
+    // remove the entry of the key, returns false if there is none
+    pub fn remove(&mut self, k: &gc::Value) -> bool {
+        let equiv = self.equiv;
+        let h = equiv.hash(k);
+
+        let empty = match self.buckets.get_mut(&h) {
+            Some(b) => match b.iter().position(|e| equiv.same(&e.0, k)) {
+                Some(i) => {
+                    b.swap_remove(i);
+                    self.count -= 1;
+                    b.is_empty()
+                }
+
+                None => return false
+            },
+
+            None => return false
+        };
+
+        if empty {
+            self.buckets.remove(&h);
+        }
+
+        true
+    }
+
+    // a copy of the entries, which stays valid if the table changes
+    pub fn entries(&self) -> Vec<(gc::Value, gc::Value)> {
+        self.buckets.values().flat_map(|b| b.iter().cloned()).collect()
+    }
+}
+
+impl gc::visit::Visitor for HashTable {
+    fn visit(&mut self, m: bool) {
+        for b in self.buckets.values_mut() {
+            for &mut (ref mut k, ref mut v) in b.iter_mut() {
+                k.visit(m);
+                v.visit(m);
+            }
+        }
+    }
+}
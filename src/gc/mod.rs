@@ -1,18 +1,27 @@
+pub use self::bytevector::Bytevector;
 pub use self::closure::Closure;
 pub use self::collect::GC;
+pub use self::condition::Condition;
 pub use self::env::Env;
+pub use self::hashtable::Equiv;
+pub use self::hashtable::HashTable;
 pub use self::pair::Pair;
 pub use self::ptr::Ptr;
 pub use self::string::String;
 pub use self::value::Value;
+pub use self::vector::Vector;
 
 pub mod ptr;
 #[macro_use]
 pub mod value;
 pub mod visit;
 
+mod bytevector;
 mod closure;
 mod collect;
+mod condition;
 mod env;
+mod hashtable;
 mod pair;
 mod string;
+mod vector;
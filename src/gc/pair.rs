@@ -13,21 +13,44 @@ pub struct Pair {
 impl gc::visit::Visitor for Pair {
     fn visit(&mut self, m: bool) {
         self.car.visit(m);
-        self.cdr.visit(m);
+
+        // follow the cdr chain iteratively, long lists would
+        // overflow the native stack otherwise
+        let mut next = match self.cdr {
+            gc::value::Pair(p) => p,
+            _ => return self.cdr.visit(m)
+        };
+
+        while next.set_mark(m) {
+            next.car.visit(m);
+            next = match next.cdr {
+                gc::value::Pair(p) => p,
+                _ => return next.cdr.visit(m)
+            };
+        }
     }
 }
 
 impl fmt::Display for Pair {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        let car = self.car.to_string();
+        let mut s = self.car.to_string();
+        let mut cdr = self.cdr.clone();
 
-        match self.cdr {
-            gc::value::Pair(p) => fmt.pad(
-                &format!("{} {}", car, (*p).to_string())
-            ),
+        loop {
+            cdr = match cdr {
+                gc::value::Pair(p) => {
+                    s.push_str(&format!(" {}", p.car));
+                    p.cdr.clone()
+                }
 
-            gc::value::Null => fmt.pad(&format!("{}", car)),
-            ref v => fmt.pad(&format!("{} . {}", car, v.to_string()))
+                gc::value::Null => break,
+                ref v => {
+                    s.push_str(&format!(" . {}", v));
+                    break
+                }
+            }
         }
+
+        fmt.pad(&s)
     }
 }
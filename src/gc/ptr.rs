@@ -38,16 +38,28 @@ impl<T> Clone for Ptr<T> {
     }
 }
 
-impl<T: gc::visit::Visitor> gc::visit::Visitor for Ptr<T> {
-    fn visit(&mut self, m: bool) {
+impl<T> Ptr<T> {
+    // mark the pointed cell, returns false if it was already marked
+    #[inline(always)]
+    pub fn set_mark(&mut self, m: bool) -> bool {
         let &mut Ptr(ptr) = self;
         unsafe {
-            let Cell { ref mut data, ref mut mark } = *ptr;
-            if *mark != m {
-                *mark = m;
-                (*data.get()).visit(m);
+            if (*ptr).mark != m {
+                (*ptr).mark = m;
+                true
+            } else {
+                false
             }
         }
+    }
+}
+
+impl<T: gc::visit::Visitor> gc::visit::Visitor for Ptr<T> {
+    fn visit(&mut self, m: bool) {
+        if self.set_mark(m) {
+            let &mut Ptr(ptr) = self;
+            unsafe { (*(*ptr).data.get()).visit(m) }
+        }
     } 
 }
 
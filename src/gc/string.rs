@@ -1,4 +1,5 @@
 use std::fmt;
+use gc;
 
 // a garbage-collected Scheme string
 
@@ -8,6 +9,11 @@ pub struct String {
     pub mutable: bool
 }
 
+impl gc::visit::Visitor for String {
+    fn visit(&mut self, _: bool) {
+    }
+}
+
 impl fmt::Display for String {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         fmt.pad(&self.str)
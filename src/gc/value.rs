@@ -3,6 +3,8 @@ use std::fmt;
 use gc;
 use gmp;
 use primitives;
+use vm::process;
+use vm::thread;
 
 pub use self::Value::*;
 
@@ -123,54 +125,114 @@ pub mod list {
 // Type for representing Scheme values manipulated by the VM
 // a Value can be either
 //   * a pair of two values (managed by the GC)
+//   * a vector of values or of bytes, managed by the GC
+//   * a hash table, managed by the GC
 //   * a closure with its program and environment managed by the GC
+//   * a condition object raised on errors, managed by the GC
+//   * threads and their synchronization objects, managed by the GC
+//   * a handle on the mailbox of a process, managed by the GC
 //   * a primitive (in-VM implemented function)
-//   * integer data types managed by copy
+//   * characters, small integers and flonums stored inline, bignums and rationals
+//     managed by copy
 //   * unit, the void value
 //   * null, a singleton value for '()
 
 // FIXME: bug #10501 #[deriving(Clone)]
 pub enum Value {
     Bool(bool),
+    Bytevector(gc::Ptr<gc::Bytevector>),
+    Char(char),
     Closure(gc::Ptr<gc::Closure>),
+    Condition(gc::Ptr<gc::Condition>),
+    CondVar(gc::Ptr<thread::CondVar>),
+    Fixnum(i64),
+    Flonum(f64),
+    HashTable(gc::Ptr<gc::HashTable>),
+    Mailbox(gc::Ptr<process::Mailbox>),
+    Mutex(gc::Ptr<thread::Mutex>),
     Null,
     Num(gmp::mpz::Mpz),
     Pair(gc::Ptr<gc::Pair>),
     Primitive(primitives::Prim, &'static str),
+    Ratio(gmp::mpq::Mpq),
     String(gc::Ptr<gc::String>),
     Symbol(gc::Ptr<gc::String>),
-    Unit
+    Thread(gc::Ptr<thread::Thread>),
+    Unit,
+    Vector(gc::Ptr<gc::Vector>)
 }
 
 impl Clone for Value {
     fn clone(&self) -> Value {
         match self {
             &Bool(b) => Bool(b),
+            &Bytevector(bv) => Bytevector(bv),
+            &Char(c) => Char(c),
             &Closure(cl) => Closure(cl),
+            &Condition(c) => Condition(c),
+            &CondVar(c) => CondVar(c),
+            &Fixnum(i) => Fixnum(i),
+            &Flonum(f) => Flonum(f),
+            &HashTable(t) => HashTable(t),
+            &Mailbox(mb) => Mailbox(mb),
+            &Mutex(m) => Mutex(m),
             &Null => Null,
             &Num(ref n) => Num(n.clone()),
             &Pair(p) => Pair(p),
             &Primitive(p, n) => Primitive(p, n),
+            &Ratio(ref q) => Ratio(q.clone()),
             &String(s) => String(s),
             &Symbol(h) => Symbol(h),
-            &Unit => Unit
+            &Thread(t) => Thread(t),
+            &Unit => Unit,
+            &Vector(v) => Vector(v)
         }
     }
 }
 
+// the external representation of a character, such as #\a or #\space,
+// as printed by write
+pub fn char_to_string(c: char) -> ::std::string::String {
+    match c {
+        '\x07' => "#\\alarm".to_string(),
+        '\x08' => "#\\backspace".to_string(),
+        '\x7f' => "#\\delete".to_string(),
+        '\x1b' => "#\\escape".to_string(),
+        '\n' => "#\\newline".to_string(),
+        '\0' => "#\\null".to_string(),
+        '\r' => "#\\return".to_string(),
+        ' ' => "#\\space".to_string(),
+        '\t' => "#\\tab".to_string(),
+        c if c.is_control() => format!("#\\x{:x}", c as u32),
+        c => format!("#\\{}", c)
+    }
+}
+
 impl fmt::Display for Value {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         match self {
             &Bool(true)      => fmt.pad("#t"),
             &Bool(false)     => fmt.pad("#f"),
+            &Bytevector(bv)  => fmt.pad(&format!("#u8({})", bv)),
+            &Char(c)         => fmt.pad(&c.to_string()),
             &Closure(_)      => fmt.pad("#<procedure>"),
+            &Condition(c)    => fmt.pad(&format!("{}", c)),
+            &CondVar(_)      => fmt.pad("#<condition-variable>"),
+            &Fixnum(i)       => fmt.pad(&format!("{}", i)),
+            &Flonum(f)       => fmt.pad(&primitives::num::flonum_to_string(f)),
+            &HashTable(_)    => fmt.pad("#<hash-table>"),
+            &Mailbox(_)      => fmt.pad("#<mailbox>"),
+            &Mutex(_)        => fmt.pad("#<mutex>"),
             &Null            => fmt.pad("'()"),
             &Num(ref i)      => fmt.pad(&format!("{}", i)),
             &Pair(p)         => fmt.pad(&format!("({})", p)),
             &Primitive(_, _) => fmt.pad("#<procedure>"),
+            &Ratio(ref q)    => fmt.pad(&format!("{}", q)),
             &String(s)       => fmt.pad(&format!("{}", s)),
             &Symbol(h)       => fmt.pad(&format!("'{}", h)),
-            &Unit            => fmt.pad("")
+            &Thread(_)       => fmt.pad("#<thread>"),
+            &Unit            => fmt.pad(""),
+            &Vector(v)       => fmt.pad(&format!("#({})", v))
         }
     }
 }
@@ -183,7 +245,15 @@ impl PartialEq for Value {
         match (self, v) {
             // eq do object-compareason
             (&Pair(p1), &Pair(p2)) => p1 == p2,
+            (&Bytevector(bv1), &Bytevector(bv2)) => bv1 == bv2,
             (&Closure(cl1), &Closure(cl2)) => *cl1 == *cl2,
+            (&Condition(c1), &Condition(c2)) => c1 == c2,
+            (&CondVar(c1), &CondVar(c2)) => c1 == c2,
+            (&Mailbox(mb1), &Mailbox(mb2)) => mb1 == mb2,
+            (&Mutex(m1), &Mutex(m2)) => m1 == m2,
+            (&Thread(t1), &Thread(t2)) => t1 == t2,
+            (&Vector(v1), &Vector(v2)) => v1 == v2,
+            (&HashTable(t1), &HashTable(t2)) => t1 == t2,
 
             (&Primitive(p1, _), &Primitive(p2, _)) => {
                 let p1: *const () = unsafe { transmute(p1) };
@@ -191,8 +261,12 @@ impl PartialEq for Value {
                 p1 == p2
             }
 
+            (&Fixnum(i), &Fixnum(j)) => i == j,
+            (&Flonum(x), &Flonum(y)) => x == y,
             (&Num(ref i), &Num(ref j)) => i == j,
+            (&Ratio(ref i), &Ratio(ref j)) => i == j,
             (&Bool(b1), &Bool(b2)) => b1 == b2,
+            (&Char(c1), &Char(c2)) => c1 == c2,
             (&Symbol(h1), &Symbol(h2)) => (h1) == (h2),
             (&String(s1), &String(s2)) => (s1) == (s2),
             (&Null, &Null) => true,
@@ -203,6 +277,16 @@ impl PartialEq for Value {
 }
 
 impl Value {
+    // eqv?, which compares numbers and characters by value. Flonums are
+    // compared by their bits, so 0.0 and -0.0 differ and a NaN is the
+    // same as itself
+    pub fn eqv(&self, other: &Value) -> bool {
+        match (self, other) {
+            (&Flonum(x), &Flonum(y)) => x.to_bits() == y.to_bits(),
+            _ => self == other
+        }
+    }
+
     // structural compareason
     pub fn compare(&self, other: &Value) -> bool {
         match (self, other) {
@@ -210,7 +294,18 @@ impl Value {
                 p1.car.compare(&p2.car) && p1.cdr.compare(&p2.cdr)
             }
 
+            (&Vector(v1), &Vector(v2)) => {
+                v1.values.len() == v2.values.len() &&
+                    v1.values.iter().zip(v2.values.iter()).all(|(a, b)| a.compare(b))
+            }
+
             (&Closure(cl1), &Closure(cl2)) => *cl1 == *cl2,
+            (&Condition(c1), &Condition(c2)) => c1 == c2,
+            (&CondVar(c1), &CondVar(c2)) => c1 == c2,
+            (&Mailbox(mb1), &Mailbox(mb2)) => mb1 == mb2,
+            (&Mutex(m1), &Mutex(m2)) => m1 == m2,
+            (&Thread(t1), &Thread(t2)) => t1 == t2,
+            (&HashTable(t1), &HashTable(t2)) => t1 == t2,
 
             (&Primitive(p1, _), &Primitive(p2, _)) => {
                 use std::mem::transmute;
@@ -219,13 +314,112 @@ impl Value {
                 p1 == p2
             }
 
+            (&Fixnum(i), &Fixnum(j)) => i == j,
+            (&Flonum(x), &Flonum(y)) => x.to_bits() == y.to_bits(),
             (&Num(ref i), &Num(ref j)) => i == j,
+            (&Ratio(ref i), &Ratio(ref j)) => i == j,
             (&Bool(b1), &Bool(b2)) => b1 == b2,
+            (&Char(c1), &Char(c2)) => c1 == c2,
             (&Symbol(h1), &Symbol(h2)) => h1.str == h2.str,
             (&String(s1), &String(s2)) => s1.str == s2.str,
+            (&Bytevector(bv1), &Bytevector(bv2)) => bv1.bytes == bv2.bytes,
             (&Null, &Null) => true,
             (&Unit, &Unit) => true,
             _ => false
         }
     }
+
+    // a hash of the value, consistent with eqv, or with compare if
+    // structural. Structures are only hashed up to a few levels and
+    // items, which also bounds the hashing of circular ones
+    pub fn hash(&self, structural: bool) -> u64 {
+        self.hash_depth(structural, 4)
+    }
+
+    fn hash_depth(&self, structural: bool, depth: u32) -> u64 {
+        let h = 0xcbf29ce484222325;
+
+        match self {
+            &Bool(b) => mix(mix(h, 1), b as u64),
+            &Bytevector(bv) if structural => hash_bytes(mix(h, 2), &bv.bytes),
+            &Char(c) => mix(mix(h, 3), c as u64),
+            &Closure(cl) => mix(mix(mix(h, 4), cl.pc), addr(cl.env)),
+            &Fixnum(i) => mix(mix(h, 5), i as u64),
+            &Flonum(f) => mix(mix(h, 6), f.to_bits()),
+            &Null => mix(h, 7),
+            &Num(ref n) => hash_bytes(mix(h, 8), n.to_str_radix(16).as_bytes()),
+            &Pair(p) if structural => {
+                let mut h = mix(h, 9);
+
+                if depth > 0 {
+                    // lists are hashed by their first items and their
+                    // tail, the spine doesn't use up the depth
+                    let mut p = p;
+
+                    for _ in 0 .. 8 {
+                        h = mix(h, p.car.hash_depth(true, depth - 1));
+
+                        let cdr = p.cdr.clone();
+                        match cdr {
+                            Pair(next) => p = next,
+                            tail => {
+                                h = mix(h, tail.hash_depth(true, depth - 1));
+                                break
+                            }
+                        }
+                    }
+                }
+
+                h
+            }
+
+            &Primitive(p, _) => mix(mix(h, 10), p as usize as u64),
+            &Ratio(ref q) => {
+                let h = hash_bytes(mix(h, 11), q.get_num().to_str_radix(16).as_bytes());
+                hash_bytes(mix(h, 11), q.get_den().to_str_radix(16).as_bytes())
+            }
+
+            &String(s) if structural => hash_bytes(mix(h, 12), s.str.as_bytes()),
+            // symbols of the same name are the same object
+            &Symbol(s) => hash_bytes(mix(h, 13), s.str.as_bytes()),
+            &Unit => mix(h, 14),
+            &Vector(v) if structural => {
+                let mut h = mix(mix(h, 15), v.values.len() as u64);
+
+                if depth > 0 {
+                    for item in v.values.iter().take(8) {
+                        h = mix(h, item.hash_depth(true, depth - 1));
+                    }
+                }
+
+                h
+            }
+
+            // other objects are only equal to themselves
+            &Bytevector(bv) => mix(h, addr(bv)),
+            &Condition(c) => mix(h, addr(c)),
+            &CondVar(c) => mix(h, addr(c)),
+            &HashTable(t) => mix(h, addr(t)),
+            &Mailbox(mb) => mix(h, addr(mb)),
+            &Mutex(m) => mix(h, addr(m)),
+            &Pair(p) => mix(h, addr(p)),
+            &String(s) => mix(h, addr(s)),
+            &Thread(t) => mix(h, addr(t)),
+            &Vector(v) => mix(h, addr(v))
+        }
+    }
+}
+
+fn mix(h: u64, x: u64) -> u64 {
+    (h ^ x).wrapping_mul(0x100000001b3)
+}
+
+fn hash_bytes(h: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(h, |h, b| mix(h, *b as u64))
+}
+
+// the collector doesn't move objects, their address is a stable hash
+fn addr<T>(p: gc::Ptr<T>) -> u64 {
+    let gc::Ptr(p) = p;
+    p as usize as u64
 }
@@ -0,0 +1,23 @@
+use std::fmt;
+use gc;
+
+// a garbage-collected Scheme vector
+
+#[repr(packed)]
+pub struct Vector {
+    pub values: Vec<gc::Value>,
+    pub mutable: bool
+}
+
+impl gc::visit::Visitor for Vector {
+    fn visit(&mut self, m: bool) {
+        self.values.visit(m);
+    }
+}
+
+impl fmt::Display for Vector {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let items: Vec<String> = self.values.iter().map(|v| v.to_string()).collect();
+        fmt.pad(&items.join(" "))
+    }
+}
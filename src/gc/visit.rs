@@ -1,6 +1,5 @@
 use gc::value;
 use vm::Frame;
-use vm::Stack;
 
 // Visitor trait that any object the GC will have to look through must
 // implement.
@@ -13,8 +12,15 @@ pub trait Visitor {
 
 impl Visitor for Frame {
     fn visit(&mut self, m: bool) {
+        // walk the caller chain iteratively, it may be too deep
+        // for the native stack
         self.env.visit(m);
-        self.caller.as_mut().map(|f| f.visit(m));
+        let mut caller = self.caller.as_mut();
+
+        while let Some(f) = caller {
+            f.env.visit(m);
+            caller = f.caller.as_mut();
+        }
     }
 }
 
@@ -22,16 +28,31 @@ impl Visitor for value::Value {
     fn visit(&mut self, m: bool) {
         match self {
             &mut value::Pair(ref mut pair) => { pair.visit(m); }
+            &mut value::Bytevector(ref mut bv) => { bv.visit(m); }
             &mut value::Closure(ref mut cl) => { cl.visit(m); }
+            &mut value::Condition(ref mut c) => { c.visit(m); }
+            &mut value::CondVar(ref mut c) => { c.visit(m); }
+            &mut value::HashTable(ref mut t) => { t.visit(m); }
+            &mut value::Mailbox(ref mut mb) => { mb.visit(m); }
+            &mut value::Mutex(ref mut mx) => { mx.visit(m); }
+            &mut value::String(ref mut s) => { s.visit(m); }
+            &mut value::Symbol(ref mut s) => { s.visit(m); }
+            &mut value::Thread(ref mut t) => { t.visit(m); }
+            &mut value::Vector(ref mut v) => { v.visit(m); }
 
-            // values other than pairs and closures
-            // doesn't need to be GC'd
+            // other values doesn't need to be GC'd
             _ => ()
         }
     }
 }
 
-impl Visitor for Stack {
+impl<T: Visitor> Visitor for Box<T> {
+    fn visit(&mut self, m: bool) {
+        (**self).visit(m);
+    }
+}
+
+impl<T: Visitor> Visitor for Vec<T> {
     fn visit(&mut self, m: bool) {
         for v in self.iter_mut() {
             v.visit(m);
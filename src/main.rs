@@ -5,6 +5,7 @@
 #![feature(const_fn)]
 
 extern crate gmp;
+extern crate libc;
 
 #[macro_use]
 extern crate log;
@@ -15,12 +16,150 @@ mod gc;
 mod primitives;
 mod vm;
 
+use std::io;
+use std::io::Write;
+use std::panic;
+use std::path::PathBuf;
+use std::process;
+
+use gc::value;
+use vm::interrupt;
+use vm::interrupt::OnInterrupt;
+
+static USAGE: &'static str = "[-L <dir>] [--image <file>] [--dump-image <file>] \
+                              [--disassemble] [--trace] \
+                              [--max-depth <frames>] [--max-stack <values>] \
+                              [--on-interrupt debug|backtrace|raise] \
+                              [--preempt <instructions>] <program>";
+
+fn limit(prog: &str, arg: Option<String>) -> usize {
+    match arg.as_ref().and_then(|s| s.parse().ok()) {
+        Some(n) => n,
+        None => panic!("usage: {} {}", prog, USAGE)
+    }
+}
+
 fn main() {
     let mut args = ::std::env::args();
-    if args.len() < 2 {
-        panic!("usage: {} <program>", args.nth(0).unwrap());
-    } else {
-        let mut vm = vm::VM::new();
-        vm.run(&args.nth(1).unwrap());
+    let prog = args.next().unwrap();
+    let mut vm = vm::VM::new();
+    let mut file = None;
+    let mut image = None;
+    let mut dump = None;
+    let mut disassemble = false;
+
+    while let Some(arg) = args.next() {
+        if arg == "-L" {
+            match args.next() {
+                Some(dir) => vm.lib_path.push(PathBuf::from(dir)),
+                None => panic!("usage: {} {}", prog, USAGE)
+            }
+        } else if arg == "--image" {
+            match args.next() {
+                Some(f) => image = Some(PathBuf::from(f)),
+                None => panic!("usage: {} {}", prog, USAGE)
+            }
+        } else if arg == "--dump-image" {
+            match args.next() {
+                Some(f) => dump = Some(PathBuf::from(f)),
+                None => panic!("usage: {} {}", prog, USAGE)
+            }
+        } else if arg == "--disassemble" {
+            disassemble = true;
+        } else if arg == "--trace" {
+            vm.trace = true;
+        } else if arg == "--max-depth" {
+            vm.max_depth = limit(&prog, args.next());
+        } else if arg == "--max-stack" {
+            vm.max_stack = limit(&prog, args.next());
+        } else if arg == "--preempt" {
+            vm.preempt = limit(&prog, args.next());
+        } else if arg == "--on-interrupt" {
+            vm.on_interrupt = match args.next() {
+                Some(ref s) if s == "debug" => OnInterrupt::Debug,
+                Some(ref s) if s == "backtrace" => OnInterrupt::Backtrace,
+                Some(ref s) if s == "raise" => OnInterrupt::Raise,
+                _ => panic!("usage: {} {}", prog, USAGE)
+            };
+        } else if file.is_none() {
+            file = Some(arg);
+        } else {
+            panic!("usage: {} {}", prog, USAGE);
+        }
+    }
+
+    let file = match file {
+        Some(f) => f,
+        None => panic!("usage: {} {}", prog, USAGE)
+    };
+
+    if disassemble {
+        match vm.disassemble(&file) {
+            Ok(()) => return,
+            Err(e) => {
+                let _ = writeln!(io::stderr(), "{}", e);
+                process::exit(1);
+            }
+        }
+    }
+
+    interrupt::install();
+
+    match image {
+        Some(ref image) => match vm.load_image(image) {
+            Ok(()) => (),
+            Err(e) => {
+                let _ = writeln!(io::stderr(), "{}: {}", image.display(), e);
+                process::exit(1);
+            }
+        },
+
+        None => ()
+    }
+
+    // other panics are bugs of the VM or unrecoverable errors, the panic
+    // message has already been printed, but the Scheme code that was
+    // running is still of interest
+    let res = {
+        let vm = &mut *vm;
+        panic::catch_unwind(panic::AssertUnwindSafe(move || vm.catch(|vm| vm.run(&file))))
+    };
+
+    let res = match res {
+        Ok(res) => res,
+        Err(_) => {
+            let _ = write!(io::stderr(), "Backtrace:\n{}", vm.backtrace());
+            process::exit(101);
+        }
+    };
+
+    match res {
+        Ok(Ok(())) => match dump {
+            Some(ref dump) => match vm.dump_image(dump) {
+                Ok(()) => (),
+                Err(e) => {
+                    let _ = writeln!(io::stderr(), "{}: {}", dump.display(), e);
+                    process::exit(1);
+                }
+            },
+
+            None => ()
+        },
+
+        Ok(Err(e)) => {
+            let _ = writeln!(io::stderr(), "{}", e);
+            process::exit(1);
+        }
+
+        Err(obj) => {
+            let mut err = io::stderr();
+            let _ = writeln!(err, "Uncaught exception: {}", obj);
+
+            if let value::Condition(c) = obj {
+                let _ = write!(err, "{}", c.backtrace);
+            }
+
+            process::exit(70);
+        }
     }
 }
@@ -1,58 +1,327 @@
+use std::cmp::Ordering;
+
 use gc;
 use gc::value;
-use gmp;
+use gc::value::list;
+use primitives::args;
+use primitives::irritant;
+use primitives::num;
+use primitives::type_error;
+use vm;
+
+fn division_by_zero(vm: &mut vm::VM, dividend: &gc::Value) -> ! {
+    let irritants = irritant(vm, dividend);
+    vm.error("arith", "division by zero".to_string(), irritants)
+}
+
+// the VM has no multiple values, procedures returning two values
+// return them as a list
+fn two_values(vm: &mut vm::VM, a: &gc::Value, b: &gc::Value) -> gc::Value {
+    let tail = value::Pair(list::cons(b, &value::Null, &mut *vm.gc));
+    value::Pair(list::cons(a, &tail, &mut *vm.gc))
+}
 
 pub fn add(argv: super::Arguments) -> gc::Value {
-    let mut res = gmp::mpz::Mpz::zero();
+    let (args, _) = args(argv, num::is_number, "a number");
+    args.iter().fold(value::Fixnum(0), |a, b| num::add(&a, b).unwrap())
+}
 
-    for i in 0 .. argv.len() {
-        match &argv[i] {
-            &value::Num(ref n) => res = &res + n,
-            _ => panic!("gc::Value is not a number")
+pub fn sub(argv: super::Arguments) -> gc::Value {
+    let (args, _) = args(argv, num::is_number, "a number");
+
+    match &args[..] {
+        [] => panic!("No arguments"),
+        [ref i] => num::neg(i).unwrap(),
+        [ref i, ref r ..] => r.iter().fold(i.clone(), |a, b| num::sub(&a, b).unwrap())
+    }
+}
+
+pub fn mul(argv: super::Arguments) -> gc::Value {
+    let (args, _) = args(argv, num::is_number, "a number");
+    args.iter().fold(value::Fixnum(1), |a, b| num::mul(&a, b).unwrap())
+}
+
+pub fn div(argv: super::Arguments) -> gc::Value {
+    let (args, vm) = args(argv, num::is_number, "a number");
+    let (mut res, r) = match &args[..] {
+        [] => panic!("No arguments"),
+        [ref i] => (value::Fixnum(1), vec!(i.clone())),
+        [ref i, ref r ..] => (i.clone(), r.to_vec())
+    };
+
+    for i in r.iter() {
+        if num::is_zero(i) {
+            division_by_zero(vm, &res)
         }
+
+        res = num::div(&res, i).unwrap();
     }
 
-    value::Num(res)
+    res
 }
 
-pub fn min(argv: super::Arguments) -> gc::Value {
-    if argv.len() == 0 {
-        panic!("No arguments")
+fn unary(argv: super::Arguments, p: fn(&gc::Value) -> bool,
+         f: fn(&gc::Value) -> Option<gc::Value>, what: &str) -> gc::Value {
+    let (args, vm) = args(argv, p, what);
+
+    match &args[..] {
+        [ref n] => match f(n) {
+            Some(v) => v,
+            None => type_error(vm, what, n)
+        },
+
+        _ => panic!("Wrong number of arguments")
     }
+}
+
+pub fn numerator(argv: super::Arguments) -> gc::Value {
+    unary(argv, num::is_number, num::numerator, "a rational number")
+}
+
+pub fn denominator(argv: super::Arguments) -> gc::Value {
+    unary(argv, num::is_number, num::denominator, "a rational number")
+}
+
+pub fn exact(argv: super::Arguments) -> gc::Value {
+    unary(argv, num::is_number, num::exact, "a finite number")
+}
+
+pub fn inexact(argv: super::Arguments) -> gc::Value {
+    unary(argv, num::is_number, num::inexact, "a number")
+}
+
+pub fn floor(argv: super::Arguments) -> gc::Value {
+    unary(argv, num::is_number, num::floor, "a number")
+}
 
-    match argv.vec() {
-        [value::Num(ref i)] => value::Num(-i),
-        [value::Num(ref i), ref r ..] => {
-            let mut res = i.clone();
+pub fn ceiling(argv: super::Arguments) -> gc::Value {
+    unary(argv, num::is_number, num::ceiling, "a number")
+}
+
+pub fn round(argv: super::Arguments) -> gc::Value {
+    unary(argv, num::is_number, num::round, "a number")
+}
+
+pub fn truncate(argv: super::Arguments) -> gc::Value {
+    unary(argv, num::is_number, num::truncate, "a number")
+}
+
+pub fn abs(argv: super::Arguments) -> gc::Value {
+    unary(argv, num::is_number, num::abs, "a number")
+}
 
-            for i in r.iter() {
-                match i {
-                    &value::Num(ref n) => res = &res - n,
-                    _ => panic!("gc::Value is not a number")
-                }
-            }
+pub fn square(argv: super::Arguments) -> gc::Value {
+    let (args, _) = args(argv, num::is_number, "a number");
 
-            value::Num(res)
+    match &args[..] {
+        [ref n] => num::mul(n, n).unwrap(),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+// true if each argument compares to the next with one of the given orderings
+fn compare(argv: super::Arguments, ok: &[Ordering]) -> gc::Value {
+    let (args, _) = args(argv, num::is_number, "a number");
+
+    for w in args.windows(2) {
+        match num::cmp(&w[0], &w[1]) {
+            Some(o) if ok.contains(&o) => (),
+            _ => return value::Bool(false)
         }
+    }
 
-        _ => panic!("gc::Value is not a number")
+    value::Bool(true)
+}
+
+pub fn lt(argv: super::Arguments) -> gc::Value {
+    compare(argv, &[Ordering::Less])
+}
+
+pub fn gt(argv: super::Arguments) -> gc::Value {
+    compare(argv, &[Ordering::Greater])
+}
+
+pub fn le(argv: super::Arguments) -> gc::Value {
+    compare(argv, &[Ordering::Less, Ordering::Equal])
+}
+
+pub fn ge(argv: super::Arguments) -> gc::Value {
+    compare(argv, &[Ordering::Greater, Ordering::Equal])
+}
+
+// the sign of the argument is one of the given orderings
+fn sign(argv: super::Arguments, ok: &[Ordering]) -> gc::Value {
+    let (args, _) = args(argv, num::is_number, "a number");
+
+    match &args[..] {
+        [ref n] => match num::cmp(n, &value::Fixnum(0)) {
+            Some(o) => value::Bool(ok.contains(&o)),
+            None => value::Bool(false)
+        },
+
+        _ => panic!("Wrong number of arguments")
     }
 }
 
-pub fn mul(argv: super::Arguments) -> gc::Value {
-    let mut res = gmp::mpz::Mpz::one();
+pub fn is_zero(argv: super::Arguments) -> gc::Value {
+    sign(argv, &[Ordering::Equal])
+}
 
-    for i in 0 .. argv.len() {
-        match &argv[i] {
-            &value::Num(ref n) => res = &res * n,
-            _ => panic!("gc::Value is not a number")
+pub fn is_positive(argv: super::Arguments) -> gc::Value {
+    sign(argv, &[Ordering::Greater])
+}
+
+pub fn is_negative(argv: super::Arguments) -> gc::Value {
+    sign(argv, &[Ordering::Less])
+}
+
+fn is_odd_integer(n: &gc::Value) -> bool {
+    !num::is_zero(&num::remainder(n, &value::Fixnum(2)).unwrap())
+}
+
+pub fn is_odd(argv: super::Arguments) -> gc::Value {
+    let (args, _) = args(argv, num::is_integer, "an integer");
+
+    match &args[..] {
+        [ref n] => value::Bool(is_odd_integer(n)),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn is_even(argv: super::Arguments) -> gc::Value {
+    let (args, _) = args(argv, num::is_integer, "an integer");
+
+    match &args[..] {
+        [ref n] => value::Bool(!is_odd_integer(n)),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+// the argument that compares to all the others with the given ordering,
+// inexact if any of them is
+fn extremum(argv: super::Arguments, keep: Ordering) -> gc::Value {
+    let (args, _) = args(argv, num::is_number, "a number");
+    let mut res = match args.first() {
+        Some(n) => n.clone(),
+        None => panic!("No arguments")
+    };
+
+    for n in args.iter() {
+        if num::cmp(n, &res) == Some(keep) {
+            res = n.clone();
         }
     }
 
-    value::Num(res)
+    if args.iter().all(num::is_exact) {
+        res
+    } else {
+        num::inexact(&res).unwrap()
+    }
 }
 
-pub fn div(_: super::Arguments) -> gc::Value {
-    // requires exact numbers implementation
-    panic!("Unimplemented")
+pub fn min(argv: super::Arguments) -> gc::Value {
+    extremum(argv, Ordering::Less)
+}
+
+pub fn max(argv: super::Arguments) -> gc::Value {
+    extremum(argv, Ordering::Greater)
+}
+
+fn division(argv: super::Arguments,
+            f: fn(&gc::Value, &gc::Value) -> Option<gc::Value>) -> gc::Value {
+    let (args, vm) = args(argv, num::is_integer, "an integer");
+
+    match &args[..] {
+        [ref n, ref d] if num::is_zero(d) => division_by_zero(vm, n),
+        [ref n, ref d] => f(n, d).unwrap(),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn quotient(argv: super::Arguments) -> gc::Value {
+    division(argv, num::quotient)
+}
+
+pub fn remainder(argv: super::Arguments) -> gc::Value {
+    division(argv, num::remainder)
+}
+
+pub fn modulo(argv: super::Arguments) -> gc::Value {
+    division(argv, num::modulo)
+}
+
+// (floor/ n d) and (truncate/ n d) give the quotient and the remainder
+fn division2(argv: super::Arguments,
+             q: fn(&gc::Value, &gc::Value) -> Option<gc::Value>,
+             r: fn(&gc::Value, &gc::Value) -> Option<gc::Value>) -> gc::Value {
+    let (args, vm) = args(argv, num::is_integer, "an integer");
+
+    match &args[..] {
+        [ref n, ref d] if num::is_zero(d) => division_by_zero(vm, n),
+        [ref n, ref d] => two_values(vm, &q(n, d).unwrap(), &r(n, d).unwrap()),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn floor_div(argv: super::Arguments) -> gc::Value {
+    division2(argv, num::floor_quotient, num::modulo)
+}
+
+pub fn truncate_div(argv: super::Arguments) -> gc::Value {
+    division2(argv, num::quotient, num::remainder)
+}
+
+pub fn floor_quotient(argv: super::Arguments) -> gc::Value {
+    division(argv, num::floor_quotient)
+}
+
+pub fn floor_remainder(argv: super::Arguments) -> gc::Value {
+    division(argv, num::modulo)
+}
+
+pub fn truncate_quotient(argv: super::Arguments) -> gc::Value {
+    division(argv, num::quotient)
+}
+
+pub fn truncate_remainder(argv: super::Arguments) -> gc::Value {
+    division(argv, num::remainder)
+}
+
+pub fn gcd(argv: super::Arguments) -> gc::Value {
+    let (args, _) = args(argv, num::is_integer, "an integer");
+    args.iter().fold(value::Fixnum(0), |a, b| num::gcd(&a, b).unwrap())
+}
+
+pub fn lcm(argv: super::Arguments) -> gc::Value {
+    let (args, _) = args(argv, num::is_integer, "an integer");
+    args.iter().fold(value::Fixnum(1), |a, b| num::lcm(&a, b).unwrap())
+}
+
+pub fn expt(argv: super::Arguments) -> gc::Value {
+    let (args, vm) = args(argv, num::is_number, "a number");
+
+    match &args[..] {
+        [ref b, ref e] if num::is_zero(b) && num::is_integer(e) &&
+            num::cmp(e, &value::Fixnum(0)) == Some(Ordering::Less) => division_by_zero(vm, b),
+        [ref b, ref e] if num::expt_bits(b, e).map_or(false, |n| n > num::MAX_BITS) => {
+            let irritants = irritant(vm, e);
+            vm.error("arith", "exact result of expt is too large".to_string(), irritants)
+        }
+
+        [ref b, ref e] => num::expt(b, e).unwrap(),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn exact_integer_sqrt(argv: super::Arguments) -> gc::Value {
+    let (args, vm) = args(argv, num::is_integer, "an integer");
+
+    match &args[..] {
+        [ref n] => match num::exact_integer_sqrt(n) {
+            Some((s, r)) => two_values(vm, &s, &r),
+            None => type_error(vm, "a nonnegative integer", n)
+        },
+
+        _ => panic!("Wrong number of arguments")
+    }
 }
@@ -0,0 +1,97 @@
+use gc;
+use gc::value;
+use primitives::args;
+use primitives::num;
+use primitives::range_error;
+use primitives::type_error;
+
+// Bitwise operations on integers, as in SRFI 151
+
+fn fold(argv: super::Arguments, init: i64,
+        f: fn(&gc::Value, &gc::Value) -> Option<gc::Value>) -> gc::Value {
+    let (args, _) = args(argv, num::is_integer, "an integer");
+    args.iter().fold(value::Fixnum(init), |a, b| f(&a, b).unwrap())
+}
+
+fn unary(argv: super::Arguments, f: fn(&gc::Value) -> Option<gc::Value>) -> gc::Value {
+    let (args, _) = args(argv, num::is_integer, "an integer");
+
+    match &args[..] {
+        [ref n] => f(n).unwrap(),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn bitwise_and(argv: super::Arguments) -> gc::Value {
+    fold(argv, -1, num::bit_and)
+}
+
+pub fn bitwise_or(argv: super::Arguments) -> gc::Value {
+    fold(argv, 0, num::bit_or)
+}
+
+pub fn bitwise_xor(argv: super::Arguments) -> gc::Value {
+    fold(argv, 0, num::bit_xor)
+}
+
+pub fn bitwise_not(argv: super::Arguments) -> gc::Value {
+    unary(argv, num::bit_not)
+}
+
+pub fn bit_count(argv: super::Arguments) -> gc::Value {
+    unary(argv, num::bit_count)
+}
+
+pub fn integer_length(argv: super::Arguments) -> gc::Value {
+    unary(argv, num::integer_length)
+}
+
+// (arithmetic-shift i count)
+pub fn arithmetic_shift(argv: super::Arguments) -> gc::Value {
+    let (args, vm) = args(argv, num::is_integer, "an integer");
+
+    match &args[..] {
+        [_, value::Fixnum(ref c)] if *c > num::MAX_BITS as i64 => {
+            range_error(vm, "shift count", &args[1])
+        }
+
+        [ref n, value::Fixnum(ref c)] => num::arithmetic_shift(n, *c).unwrap(),
+        [_, ref c] => type_error(vm, "a shift count", c),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+// (bit-set? index i)
+pub fn bit_set(argv: super::Arguments) -> gc::Value {
+    let (args, vm) = args(argv, num::is_integer, "an integer");
+
+    match &args[..] {
+        [value::Fixnum(ref i), ref n] if *i >= 0 => {
+            value::Bool(num::bit_set(*i as usize, n).unwrap())
+        }
+
+        [ref i, _] => type_error(vm, "a bit index", i),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+// (copy-bit index i boolean)
+pub fn copy_bit(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [_, ref n, _] if !num::is_integer(n) => type_error(vm, "an integer", n),
+        [value::Fixnum(ref i), _, _] if *i > num::MAX_BITS as i64 => {
+            range_error(vm, "bit index", &args[0])
+        }
+
+        [value::Fixnum(ref i), ref n, value::Bool(ref b)] if *i >= 0 => {
+            num::copy_bit(*i as usize, n, *b).unwrap()
+        }
+
+        [value::Fixnum(ref i), _, ref b] if *i >= 0 => type_error(vm, "a boolean", b),
+        [ref i, _, _] => type_error(vm, "a bit index", i),
+        _ => panic!("Wrong number of arguments")
+    }
+}
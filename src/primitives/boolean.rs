@@ -1,22 +1,11 @@
 use gc;
 use gc::value;
+use primitives::args;
+use primitives::num;
 
 pub fn cmp(argv: super::Arguments) -> gc::Value {
-    match argv.vec() {
-        [value::Num(ref v), ref r ..] => {
-            for i in r.iter() {
-                match i {
-                    &value::Num(ref v2) if *v2 == *v => (),
-                    &value::Num(_) => return value::Bool(false),
-                    _ => panic!("Bad argument")
-                }
-            }
-
-            value::Bool(true)
-        }
-
-        _ => panic!("Bad argument")
-    }
+    let (args, _) = args(argv, num::is_number, "a number");
+    value::Bool(args.windows(2).all(|w| num::eq(&w[0], &w[1]) == Some(true)))
 }
 
 pub fn eq(argv: super::Arguments) -> gc::Value {
@@ -26,6 +15,13 @@ pub fn eq(argv: super::Arguments) -> gc::Value {
     }
 }
 
+pub fn eqv(argv: super::Arguments) -> gc::Value {
+    match argv.vec() {
+        [ref v1, ref v2] => value::Bool(v1.eqv(v2)),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
 pub fn equal(argv: super::Arguments) -> gc::Value {
     match argv.vec() {
         [ref v1, ref v2] => value::Bool(v1.compare(v2)),
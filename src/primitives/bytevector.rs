@@ -0,0 +1,233 @@
+use gc;
+use gc::value;
+use primitives::args;
+use primitives::index;
+use primitives::irritant;
+use primitives::length;
+use primitives::range;
+use primitives::range_error;
+use primitives::type_error;
+use primitives::string;
+use vm;
+
+// Bytevectors
+// fixed-size arrays of bytes, for binary data. Bytevectors created by
+// programs are mutable, literals are not
+
+fn is_bytevector(v: &gc::Value) -> bool {
+    match v {
+        &value::Bytevector(_) => true,
+        _ => false
+    }
+}
+
+fn is_byte(v: &gc::Value) -> bool {
+    match v {
+        &value::Fixnum(b) => b >= 0 && b < 256,
+        _ => false
+    }
+}
+
+fn new_bytevector(vm: &mut vm::VM, bytes: Vec<u8>) -> gc::Value {
+    value::Bytevector(vm.gc.alloc(gc::Bytevector { bytes: bytes, mutable: true }))
+}
+
+fn ptr(vm: &mut vm::VM, v: &gc::Value) -> gc::Ptr<gc::Bytevector> {
+    match v {
+        &value::Bytevector(bv) => bv,
+        v => type_error(vm, "a bytevector", v)
+    }
+}
+
+// a bytevector about to be modified
+fn mutable_ptr(vm: &mut vm::VM, v: &gc::Value) -> gc::Ptr<gc::Bytevector> {
+    let bv = ptr(vm, v);
+
+    if !bv.mutable {
+        let irritants = irritant(vm, v);
+        vm.error("bytevector", "bytevector is immutable".to_string(), irritants)
+    }
+
+    bv
+}
+
+fn byte(vm: &mut vm::VM, v: &gc::Value) -> u8 {
+    match v {
+        &value::Fixnum(b) if is_byte(v) => b as u8,
+        v => type_error(vm, "a byte", v)
+    }
+}
+
+pub fn is_bytevector_value(argv: super::Arguments) -> gc::Value {
+    match argv.vec() {
+        [ref v] => value::Bool(is_bytevector(v)),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+// (make-bytevector k [byte])
+pub fn make_bytevector(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    let (k, b) = match &args[..] {
+        [ref k] => (length(vm, k), 0),
+        [ref k, ref b] => (length(vm, k), byte(vm, b)),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    new_bytevector(vm, vec![b; k])
+}
+
+pub fn bytevector(argv: super::Arguments) -> gc::Value {
+    let (args, vm) = args(argv, is_byte, "a byte");
+    let bytes = args.iter().map(|b| byte(vm, b)).collect();
+    new_bytevector(vm, bytes)
+}
+
+pub fn bytevector_length(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref bv] => value::Fixnum(ptr(vm, bv).bytes.len() as i64),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn bytevector_u8_ref(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref bv, ref k] => {
+            let bv = ptr(vm, bv);
+            let k = index(vm, k);
+
+            match bv.bytes.get(k) {
+                Some(b) => value::Fixnum(*b as i64),
+                None => range_error(vm, "index", &args[1])
+            }
+        }
+
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn bytevector_u8_set(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref bv, ref k, ref b] => {
+            let mut bv = mutable_ptr(vm, bv);
+            let k = index(vm, k);
+            let b = byte(vm, b);
+
+            if k >= bv.bytes.len() {
+                range_error(vm, "index", &args[1])
+            }
+
+            bv.bytes[k] = b;
+        }
+
+        _ => panic!("Wrong number of arguments")
+    }
+
+    value::Unit
+}
+
+// (bytevector-copy bytevector [start [end]])
+pub fn bytevector_copy(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref bv, ref bounds ..] if bounds.len() <= 2 => {
+            let bv = ptr(vm, bv);
+            let (b, e) = range(vm, bv.bytes.len(), bounds);
+            new_bytevector(vm, bv.bytes[b .. e].to_vec())
+        }
+
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+// (bytevector-copy! to at from [start [end]]), the bytevectors may
+// be the same
+pub fn bytevector_copy_to(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref to, ref at, ref from, ref bounds ..] if bounds.len() <= 2 => {
+            let mut to = mutable_ptr(vm, to);
+            let at = index(vm, at);
+            let from = ptr(vm, from);
+            let (b, e) = range(vm, from.bytes.len(), bounds);
+
+            if at > to.bytes.len() || e - b > to.bytes.len() - at {
+                range_error(vm, "index", &args[1])
+            }
+
+            let bytes = from.bytes[b .. e].to_vec();
+            for (i, b) in bytes.into_iter().enumerate() {
+                to.bytes[at + i] = b;
+            }
+        }
+
+        _ => panic!("Wrong number of arguments")
+    }
+
+    value::Unit
+}
+
+pub fn bytevector_append(argv: super::Arguments) -> gc::Value {
+    let (args, vm) = args(argv, is_bytevector, "a bytevector");
+    let mut res = vec!();
+
+    for bv in args.iter() {
+        res.extend(ptr(vm, bv).bytes.iter().cloned());
+    }
+
+    new_bytevector(vm, res)
+}
+
+// (utf8->string bytevector [start [end]])
+pub fn utf8_to_string(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref bv, ref bounds ..] if bounds.len() <= 2 => {
+            let p = ptr(vm, bv);
+            let (b, e) = range(vm, p.bytes.len(), bounds);
+
+            match ::std::str::from_utf8(&p.bytes[b .. e]) {
+                Ok(s) => string::new_string(vm, s.to_string()),
+                Err(_) => {
+                    let irritants = irritant(vm, bv);
+                    vm.error("string", "invalid UTF-8 sequence".to_string(), irritants)
+                }
+            }
+        }
+
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+// (string->utf8 string [start [end]])
+pub fn string_to_utf8(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref s, ref bounds ..] if bounds.len() <= 2 => {
+            let s = string::string(vm, s);
+            let (b, e) = string::range(vm, s, bounds);
+            new_bytevector(vm, s[b .. e].as_bytes().to_vec())
+        }
+
+        _ => panic!("Wrong number of arguments")
+    }
+}
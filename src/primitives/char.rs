@@ -0,0 +1,154 @@
+use gc;
+use gc::value;
+use primitives::args;
+use primitives::type_error;
+
+// Characters
+
+fn is_char(v: &gc::Value) -> bool {
+    match v {
+        &value::Char(_) => true,
+        _ => false
+    }
+}
+
+// the characters given as arguments
+fn chars(argv: super::Arguments) -> Vec<char> {
+    let (args, _) = args(argv, is_char, "a character");
+
+    args.iter().map(|v| match v {
+        &value::Char(c) => c,
+        _ => unreachable!()
+    }).collect()
+}
+
+fn one_char(argv: super::Arguments) -> char {
+    match &chars(argv)[..] {
+        [ref c] => *c,
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+// case conversions that change the length of a character, such as
+// the upper case of a sharp s, leave it unchanged
+fn single<I: Iterator<Item = char>>(c: char, mut it: I) -> char {
+    match (it.next(), it.next()) {
+        (Some(r), None) => r,
+        _ => c
+    }
+}
+
+fn upcase(c: char) -> char {
+    single(c, c.to_uppercase())
+}
+
+fn downcase(c: char) -> char {
+    single(c, c.to_lowercase())
+}
+
+fn foldcase(c: char) -> char {
+    downcase(upcase(c))
+}
+
+pub fn is_char_value(argv: super::Arguments) -> gc::Value {
+    match argv.vec() {
+        [ref v] => value::Bool(is_char(v)),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn char_to_integer(argv: super::Arguments) -> gc::Value {
+    value::Fixnum(one_char(argv) as i64)
+}
+
+pub fn integer_to_char(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [value::Fixnum(ref i)] if *i >= 0 && *i <= 0x10FFFF => {
+            match ::std::char::from_u32(*i as u32) {
+                Some(c) => value::Char(c),
+                None => type_error(vm, "a Unicode scalar value", &args[0])
+            }
+        }
+
+        [ref v] => type_error(vm, "a Unicode scalar value", v),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+// true if each character compares to the next with f
+fn compare(argv: super::Arguments, f: fn(&char, &char) -> bool, fold: bool) -> gc::Value {
+    let mut cs = chars(argv);
+
+    if fold {
+        cs = cs.into_iter().map(foldcase).collect();
+    }
+
+    value::Bool(cs.windows(2).all(|w| f(&w[0], &w[1])))
+}
+
+pub fn char_eq(argv: super::Arguments) -> gc::Value {
+    compare(argv, PartialEq::eq, false)
+}
+
+pub fn char_lt(argv: super::Arguments) -> gc::Value {
+    compare(argv, PartialOrd::lt, false)
+}
+
+pub fn char_gt(argv: super::Arguments) -> gc::Value {
+    compare(argv, PartialOrd::gt, false)
+}
+
+pub fn char_le(argv: super::Arguments) -> gc::Value {
+    compare(argv, PartialOrd::le, false)
+}
+
+pub fn char_ge(argv: super::Arguments) -> gc::Value {
+    compare(argv, PartialOrd::ge, false)
+}
+
+pub fn char_ci_eq(argv: super::Arguments) -> gc::Value {
+    compare(argv, PartialEq::eq, true)
+}
+
+pub fn char_ci_lt(argv: super::Arguments) -> gc::Value {
+    compare(argv, PartialOrd::lt, true)
+}
+
+pub fn char_ci_gt(argv: super::Arguments) -> gc::Value {
+    compare(argv, PartialOrd::gt, true)
+}
+
+pub fn char_ci_le(argv: super::Arguments) -> gc::Value {
+    compare(argv, PartialOrd::le, true)
+}
+
+pub fn char_ci_ge(argv: super::Arguments) -> gc::Value {
+    compare(argv, PartialOrd::ge, true)
+}
+
+pub fn char_upcase(argv: super::Arguments) -> gc::Value {
+    value::Char(upcase(one_char(argv)))
+}
+
+pub fn char_downcase(argv: super::Arguments) -> gc::Value {
+    value::Char(downcase(one_char(argv)))
+}
+
+pub fn char_foldcase(argv: super::Arguments) -> gc::Value {
+    value::Char(foldcase(one_char(argv)))
+}
+
+pub fn char_alphabetic(argv: super::Arguments) -> gc::Value {
+    value::Bool(one_char(argv).is_alphabetic())
+}
+
+pub fn char_numeric(argv: super::Arguments) -> gc::Value {
+    value::Bool(one_char(argv).is_numeric())
+}
+
+pub fn char_whitespace(argv: super::Arguments) -> gc::Value {
+    value::Bool(one_char(argv).is_whitespace())
+}
@@ -0,0 +1,100 @@
+use gc;
+use gc::value;
+
+pub fn with_exception_handler(argv: super::Arguments) -> gc::Value {
+    let (handler, thunk) = match argv.vec() {
+        [ref handler, ref thunk] => (handler.clone(), thunk.clone()),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    // the handler is called once the stack has been unwound to this
+    // point, and its result is returned in place of the thunk's
+    let vm = argv.vm;
+    match vm.catch(|vm| vm.fun_call_ret(&thunk, 0)) {
+        Ok(ret) => ret,
+        Err(obj) => {
+            vm.stack.push(obj);
+            vm.fun_call_ret(&handler, 1)
+        }
+    }
+}
+
+pub fn raise(argv: super::Arguments) -> gc::Value {
+    let obj = match argv.vec() {
+        [ref obj] => obj.clone(),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    argv.vm.raise(obj)
+}
+
+pub fn error(argv: super::Arguments) -> gc::Value {
+    let (msg, irritants) = match argv.vec() {
+        [value::String(s), ref r ..] => (s.str.clone(), r.to_vec()),
+        [ref v, ref r ..] => (v.to_string(), r.to_vec()),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    let mut builder = ::gc::value::list::LIST_BUILDER.clone();
+    builder.init();
+    for v in irritants.iter() {
+        builder.append(v, &mut *argv.vm.gc);
+    }
+
+    argv.vm.error("error", msg, builder.get_list())
+}
+
+pub fn is_error_object(argv: super::Arguments) -> gc::Value {
+    match argv.vec() {
+        [value::Condition(_)] => value::Bool(true),
+        [_] => value::Bool(false),
+        _ => panic!("Bad arguments")
+    }
+}
+
+pub fn error_object_message(argv: super::Arguments) -> gc::Value {
+    let msg = match argv.vec() {
+        [value::Condition(c)] => c.message.clone(),
+        [_] => panic!("Argument is not an error object"),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    value::String(argv.vm.gc.alloc(gc::String { str: msg, mutable: false }))
+}
+
+pub fn error_object_irritants(argv: super::Arguments) -> gc::Value {
+    match argv.vec() {
+        [value::Condition(c)] => c.irritants.clone(),
+        [_] => panic!("Argument is not an error object"),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+// the backtrace captured when a condition was raised, as a list of
+// strings, innermost frame first. Other raised objects have none
+pub fn condition_backtrace(argv: super::Arguments) -> gc::Value {
+    let lines = match argv.vec() {
+        [value::Condition(c)] => c.backtrace.lines(),
+        [_] => vec!(),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    let mut builder = ::gc::value::list::LIST_BUILDER.clone();
+    builder.init();
+    for l in lines.into_iter() {
+        let s = value::String(argv.vm.gc.alloc(gc::String { str: l, mutable: false }));
+        builder.append(&s, &mut *argv.vm.gc);
+    }
+
+    builder.get_list()
+}
+
+pub fn condition_kind(argv: super::Arguments) -> gc::Value {
+    let kind = match argv.vec() {
+        [value::Condition(c)] => c.kind,
+        [_] => panic!("Argument is not an error object"),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    value::Symbol(argv.vm.gc.intern(kind.to_string()))
+}
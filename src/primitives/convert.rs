@@ -1,4 +1,9 @@
 use gc;
+use primitives::num;
+use primitives::range_error;
+use primitives::string;
+use primitives::type_error;
+use vm;
 
 pub fn symbol_to_string(argv: super::Arguments) -> gc::Value {
     match argv.vec() {
@@ -17,3 +22,49 @@ pub fn string_to_symbol(argv: super::Arguments) -> gc::Value {
 
     gc::value::Symbol(argv.vm.gc.intern(sym))
 }
+
+fn radix(vm: &mut vm::VM, v: &gc::Value) -> u8 {
+    match v {
+        &gc::value::Fixnum(r) if r == 2 || r == 8 || r == 10 || r == 16 => r as u8,
+        &gc::value::Fixnum(_) => range_error(vm, "radix", v),
+        v => type_error(vm, "a radix", v)
+    }
+}
+
+// (number->string z [radix]), inexact numbers are only written in radix 10
+pub fn number_to_string(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    let (z, r) = match &args[..] {
+        [ref z] => (z, 10),
+        [ref z, ref r] => (z, radix(vm, r)),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    if !num::is_number(z) {
+        type_error(vm, "a number", z)
+    }
+
+    match num::to_string_radix(z, r) {
+        Some(s) => string::new_string(vm, s),
+        None => type_error(vm, "an exact number", z)
+    }
+}
+
+// (string->number string [radix]), #f if string is not a number
+pub fn string_to_number(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    let (s, r) = match &args[..] {
+        [ref s] => (s, 10),
+        [ref s, ref r] => (s, radix(vm, r)),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    match num::parse(string::string(vm, s), r as u32) {
+        Some(n) => n,
+        None => gc::value::Bool(false)
+    }
+}
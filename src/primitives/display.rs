@@ -1,11 +1,79 @@
 use gc;
+use gc::value;
 
 pub fn display(argv: super::Arguments) -> gc::Value {
-    print!("{}", argv[0].to_string());
-    gc::value::Unit
+    print!("{}", argv[0]);
+    value::Unit
 }
 
 pub fn newline(_: super::Arguments) -> gc::Value {
     print!("\n");
-    gc::value::Unit
+    value::Unit
+}
+
+// the external representation of a string, between double quotes
+fn string_to_string(s: &str) -> String {
+    let mut ret = String::from("\"");
+
+    for c in s.chars() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            '\n' => ret.push_str("\\n"),
+            '\t' => ret.push_str("\\t"),
+            '\r' => ret.push_str("\\r"),
+            c if c.is_control() => ret.push_str(&format!("\\x{:x};", c as u32)),
+            c => ret.push(c)
+        }
+    }
+
+    ret.push('"');
+    ret
+}
+
+// the representation of a value printed by write, which differs from
+// the one printed by display for characters and strings, including
+// those in lists and vectors
+fn written(v: &gc::Value) -> String {
+    match v {
+        &value::Char(c) => value::char_to_string(c),
+        &value::String(s) => string_to_string(&s.str),
+
+        &value::Pair(p) => {
+            let mut s = format!("({}", written(&p.car));
+            let mut cdr = p.cdr.clone();
+
+            loop {
+                cdr = match cdr {
+                    value::Pair(p) => {
+                        s.push(' ');
+                        s.push_str(&written(&p.car));
+                        p.cdr.clone()
+                    }
+
+                    value::Null => break,
+                    ref v => {
+                        s.push_str(" . ");
+                        s.push_str(&written(v));
+                        break
+                    }
+                }
+            }
+
+            s.push(')');
+            s
+        }
+
+        &value::Vector(v) => {
+            let items: Vec<String> = v.values.iter().map(written).collect();
+            format!("#({})", items.join(" "))
+        }
+
+        v => v.to_string()
+    }
+}
+
+pub fn write(argv: super::Arguments) -> gc::Value {
+    print!("{}", written(&argv[0]));
+    value::Unit
 }
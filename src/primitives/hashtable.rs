@@ -0,0 +1,296 @@
+use gc;
+use gc::value;
+use gc::value::list;
+use primitives::irritant;
+use primitives::type_error;
+use vm;
+
+// Hash tables
+// tables compare their keys with eq?, eqv?, equal? or string=?, following
+// SRFI 69. The procedures given to hash-table-walk and the updating
+// primitives may modify the table
+
+fn is_hash_table(v: &gc::Value) -> bool {
+    match v {
+        &value::HashTable(_) => true,
+        _ => false
+    }
+}
+
+fn table(vm: &mut vm::VM, v: &gc::Value) -> gc::Ptr<gc::HashTable> {
+    match v {
+        &value::HashTable(t) => t,
+        v => type_error(vm, "a hash table", v)
+    }
+}
+
+// the equivalence of the given predicate
+fn equiv(vm: &mut vm::VM, v: &gc::Value) -> gc::Equiv {
+    match v {
+        &value::Primitive(_, "eq?") => gc::Equiv::Eq,
+        &value::Primitive(_, "eqv?") => gc::Equiv::Eqv,
+        &value::Primitive(_, "equal?") => gc::Equiv::Equal,
+        &value::Primitive(_, "string=?") => gc::Equiv::String,
+        v => type_error(vm, "an equivalence predicate", v)
+    }
+}
+
+// the key, checked against the keys the table accepts
+fn key(vm: &mut vm::VM, t: gc::Ptr<gc::HashTable>, k: &gc::Value) -> gc::Value {
+    match (t.equiv, k) {
+        (gc::Equiv::String, &value::String(_)) => k.clone(),
+        (gc::Equiv::String, k) => type_error(vm, "a string", k),
+        _ => k.clone()
+    }
+}
+
+fn no_key(vm: &mut vm::VM, k: &gc::Value) -> ! {
+    let irritants = irritant(vm, k);
+    vm.error("hash-table", "no such key in hash table".to_string(), irritants)
+}
+
+fn call(vm: &mut vm::VM, fun: &gc::Value, args: &[gc::Value]) -> gc::Value {
+    // the arguments of the function are passed on the stack
+    for a in args.iter() {
+        vm.stack.push(a.clone());
+    }
+
+    vm.fun_call_ret(fun, args.len() as u8)
+}
+
+pub fn is_hash_table_value(argv: super::Arguments) -> gc::Value {
+    match argv.vec() {
+        [ref v] => value::Bool(is_hash_table(v)),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+// (make-hash-table [equiv [hash]]), a hash function may be given but
+// the hash of the equivalence is always used
+pub fn make_hash_table(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    let equiv = match &args[..] {
+        [] => gc::Equiv::Equal,
+        [ref e] | [ref e, _] => equiv(vm, e),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    value::HashTable(vm.gc.alloc(gc::HashTable::new(equiv)))
+}
+
+// (hash-table-ref table key [failure [success]])
+pub fn hash_table_ref(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref t, ref k, ref procs ..] if procs.len() <= 2 => {
+            let t = table(vm, t);
+            let k = key(vm, t, k);
+
+            match (t.get(&k), procs.get(0), procs.get(1)) {
+                (Some(v), _, Some(success)) => call(vm, success, &[v]),
+                (Some(v), _, None) => v,
+                (None, Some(failure), _) => call(vm, failure, &[]),
+                (None, None, _) => no_key(vm, &k)
+            }
+        }
+
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn hash_table_ref_default(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref t, ref k, ref default] => {
+            let t = table(vm, t);
+            let k = key(vm, t, k);
+
+            match t.get(&k) {
+                Some(v) => v,
+                None => default.clone()
+            }
+        }
+
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn hash_table_set(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref t, ref k, ref v] => {
+            let mut t = table(vm, t);
+            let k = key(vm, t, k);
+            t.insert(&k, v);
+        }
+
+        _ => panic!("Wrong number of arguments")
+    }
+
+    value::Unit
+}
+
+pub fn hash_table_delete(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref t, ref k] => {
+            let mut t = table(vm, t);
+            let k = key(vm, t, k);
+            t.remove(&k);
+        }
+
+        _ => panic!("Wrong number of arguments")
+    }
+
+    value::Unit
+}
+
+pub fn hash_table_contains(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref t, ref k] => {
+            let t = table(vm, t);
+            let k = key(vm, t, k);
+            value::Bool(t.get(&k).is_some())
+        }
+
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+// (hash-table-update! table key proc [failure])
+pub fn hash_table_update(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref t, ref k, ref fun, ref failure ..] if failure.len() <= 1 => {
+            let mut t = table(vm, t);
+            let k = key(vm, t, k);
+
+            let old = match (t.get(&k), failure.get(0)) {
+                (Some(v), _) => v,
+                (None, Some(failure)) => call(vm, failure, &[]),
+                (None, None) => no_key(vm, &k)
+            };
+
+            let new = call(vm, fun, &[old]);
+            t.insert(&k, &new);
+        }
+
+        _ => panic!("Wrong number of arguments")
+    }
+
+    value::Unit
+}
+
+// (hash-table-update!/default table key proc default)
+pub fn hash_table_update_default(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref t, ref k, ref fun, ref default] => {
+            let mut t = table(vm, t);
+            let k = key(vm, t, k);
+
+            let old = match t.get(&k) {
+                Some(v) => v,
+                None => default.clone()
+            };
+
+            let new = call(vm, fun, &[old]);
+            t.insert(&k, &new);
+        }
+
+        _ => panic!("Wrong number of arguments")
+    }
+
+    value::Unit
+}
+
+// (hash-table-walk table proc), calling proc with each key and value
+pub fn hash_table_walk(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref t, ref fun] => {
+            let t = table(vm, t);
+
+            for (k, v) in t.entries().into_iter() {
+                call(vm, fun, &[k, v]);
+            }
+        }
+
+        _ => panic!("Wrong number of arguments")
+    }
+
+    value::Unit
+}
+
+pub fn hash_table_count(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref t] => value::Fixnum(table(vm, t).len() as i64),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+// a list built from the entries of the table argument
+fn entries(argv: super::Arguments,
+           f: fn(&mut vm::VM, gc::Value, gc::Value) -> gc::Value) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    let entries = match &args[..] {
+        [ref t] => table(vm, t).entries(),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    let mut ret = value::Null;
+    for (k, v) in entries.into_iter() {
+        let item = f(vm, k, v);
+        ret = value::Pair(list::cons(&item, &ret, &mut *vm.gc));
+    }
+
+    ret
+}
+
+fn entry_key(_: &mut vm::VM, k: gc::Value, _: gc::Value) -> gc::Value {
+    k
+}
+
+fn entry_value(_: &mut vm::VM, _: gc::Value, v: gc::Value) -> gc::Value {
+    v
+}
+
+fn entry_pair(vm: &mut vm::VM, k: gc::Value, v: gc::Value) -> gc::Value {
+    value::Pair(list::cons(&k, &v, &mut *vm.gc))
+}
+
+pub fn hash_table_keys(argv: super::Arguments) -> gc::Value {
+    entries(argv, entry_key)
+}
+
+pub fn hash_table_values(argv: super::Arguments) -> gc::Value {
+    entries(argv, entry_value)
+}
+
+pub fn hash_table_to_alist(argv: super::Arguments) -> gc::Value {
+    entries(argv, entry_pair)
+}
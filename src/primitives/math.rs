@@ -0,0 +1,82 @@
+use gc;
+use gc::value;
+use primitives::args;
+use primitives::num;
+
+// The inexact math library, exact arguments are converted to flonums
+
+// the numeric arguments, as flonums
+fn flonums(argv: super::Arguments) -> Vec<f64> {
+    let (args, _) = args(argv, num::is_number, "a number");
+    args.iter().map(|x| num::to_f64(x).unwrap()).collect()
+}
+
+fn inexact(argv: super::Arguments, f: fn(f64) -> f64) -> gc::Value {
+    match &flonums(argv)[..] {
+        [ref x] => value::Flonum(f(*x)),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn sqrt(argv: super::Arguments) -> gc::Value {
+    let (args, _) = args(argv, num::is_number, "a number");
+
+    match &args[..] {
+        [ref x] => num::sqrt(x).unwrap(),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn exp(argv: super::Arguments) -> gc::Value {
+    inexact(argv, f64::exp)
+}
+
+// (log z [base])
+pub fn log(argv: super::Arguments) -> gc::Value {
+    match &flonums(argv)[..] {
+        [ref x] => value::Flonum(x.ln()),
+        [ref x, ref b] => value::Flonum(x.ln() / b.ln()),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn sin(argv: super::Arguments) -> gc::Value {
+    inexact(argv, f64::sin)
+}
+
+pub fn cos(argv: super::Arguments) -> gc::Value {
+    inexact(argv, f64::cos)
+}
+
+pub fn tan(argv: super::Arguments) -> gc::Value {
+    inexact(argv, f64::tan)
+}
+
+// (atan y [x])
+pub fn atan(argv: super::Arguments) -> gc::Value {
+    match &flonums(argv)[..] {
+        [ref y] => value::Flonum(y.atan()),
+        [ref y, ref x] => value::Flonum(y.atan2(*x)),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn is_nan(argv: super::Arguments) -> gc::Value {
+    let (args, _) = args(argv, num::is_number, "a number");
+
+    match &args[..] {
+        [value::Flonum(x)] => value::Bool(x.is_nan()),
+        [_] => value::Bool(false),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn is_infinite(argv: super::Arguments) -> gc::Value {
+    let (args, _) = args(argv, num::is_number, "a number");
+
+    match &args[..] {
+        [value::Flonum(x)] => value::Bool(x.is_infinite()),
+        [_] => value::Bool(false),
+        _ => panic!("Wrong number of arguments")
+    }
+}
@@ -1,5 +1,6 @@
 use std::ops;
 use gc;
+use gc::value;
 use vm;
 
 // public primitives
@@ -7,66 +8,347 @@ use vm;
 pub use self::list::list;
 
 mod arith;
+mod bitwise;
 mod boolean;
+mod bytevector;
+mod char;
+mod condition;
 mod control;
 mod convert;
 mod display;
+mod hashtable;
 mod list;
+mod math;
+pub mod num;
 mod pair;
+mod process;
+mod string;
+mod thread;
 mod types;
+mod vector;
 
 pub type Prim = fn(argv: Arguments) -> gc::Value;
 
+// a primitive, as known by compiled programs, and the number of
+// arguments it requires. Variadic primitives accept more
+pub struct Descr {
+    pub name: &'static str,
+    pub fun: Prim,
+    pub arity: u8,
+    pub variadic: bool
+}
+
+// all the primitives, in the order of the primitive environment, which
+// is still used by programs refering to primitives by their position
+// new primitives must be appended
+pub static TABLE: &'static [Descr] = &[
+    /* arith primitives */
+    Descr { name: "+", fun: arith::add, arity: 0, variadic: true },
+    Descr { name: "-", fun: arith::sub, arity: 1, variadic: true },
+    Descr { name: "*", fun: arith::mul, arity: 0, variadic: true },
+    Descr { name: "/", fun: arith::div, arity: 1, variadic: true },
+
+    /* boolean primitives */
+    Descr { name: "=", fun: boolean::cmp, arity: 1, variadic: true },
+    Descr { name: "eq?", fun: boolean::eq, arity: 2, variadic: false },
+    Descr { name: "equal?", fun: boolean::equal, arity: 2, variadic: false },
+
+    /* type predicates */
+    Descr { name: "boolean?", fun: types::boolean, arity: 1, variadic: false },
+    Descr { name: "null?", fun: types::null, arity: 1, variadic: false },
+    Descr { name: "pair?", fun: types::pair, arity: 1, variadic: false },
+    Descr { name: "procedure?", fun: types::procedure, arity: 1, variadic: false },
+    Descr { name: "symbol?", fun: types::symbol, arity: 1, variadic: false },
+    Descr { name: "number?", fun: types::number, arity: 1, variadic: false },
+
+    /* type converters */
+    Descr { name: "symbol->string", fun: convert::symbol_to_string,
+            arity: 1, variadic: false },
+    Descr { name: "string->symbol", fun: convert::string_to_symbol,
+            arity: 1, variadic: false },
+
+    /* pair utils */
+    Descr { name: "cons", fun: pair::cons, arity: 2, variadic: false },
+    Descr { name: "car", fun: pair::car, arity: 1, variadic: false },
+    Descr { name: "cdr", fun: pair::cdr, arity: 1, variadic: false },
+    Descr { name: "set-car!", fun: pair::setcar, arity: 2, variadic: false },
+    Descr { name: "set-cdr!", fun: pair::setcdr, arity: 2, variadic: false },
+
+    /* list utils */
+    Descr { name: "list", fun: list, arity: 0, variadic: true },
+    Descr { name: "list?", fun: list::is_list, arity: 1, variadic: false },
+    Descr { name: "map", fun: list::map, arity: 2, variadic: false },
+    Descr { name: "filter", fun: list::filter, arity: 2, variadic: false },
+
+    /* display */
+    Descr { name: "display", fun: display::display, arity: 1, variadic: false },
+    Descr { name: "newline", fun: display::newline, arity: 0, variadic: false },
+
+    /* misc */
+    Descr { name: "exit", fun: control::exit, arity: 0, variadic: true },
+    Descr { name: "assert", fun: control::assert, arity: 1, variadic: false },
+
+    /* conditions */
+    Descr { name: "with-exception-handler", fun: condition::with_exception_handler,
+            arity: 2, variadic: false },
+    Descr { name: "raise", fun: condition::raise, arity: 1, variadic: false },
+    Descr { name: "error", fun: condition::error, arity: 1, variadic: true },
+    Descr { name: "error-object?", fun: condition::is_error_object,
+            arity: 1, variadic: false },
+    Descr { name: "error-object-message", fun: condition::error_object_message,
+            arity: 1, variadic: false },
+    Descr { name: "error-object-irritants", fun: condition::error_object_irritants,
+            arity: 1, variadic: false },
+    Descr { name: "condition-kind", fun: condition::condition_kind,
+            arity: 1, variadic: false },
+
+    /* threads */
+    Descr { name: "make-thread", fun: thread::make_thread, arity: 1, variadic: true },
+    Descr { name: "thread?", fun: thread::is_thread, arity: 1, variadic: false },
+    Descr { name: "current-thread", fun: thread::current_thread,
+            arity: 0, variadic: false },
+    Descr { name: "thread-name", fun: thread::thread_name, arity: 1, variadic: false },
+    Descr { name: "thread-start!", fun: thread::thread_start,
+            arity: 1, variadic: false },
+    Descr { name: "thread-yield!", fun: thread::thread_yield,
+            arity: 0, variadic: false },
+    Descr { name: "thread-join!", fun: thread::thread_join, arity: 1, variadic: false },
+    Descr { name: "make-mutex", fun: thread::make_mutex, arity: 0, variadic: true },
+    Descr { name: "mutex?", fun: thread::is_mutex, arity: 1, variadic: false },
+    Descr { name: "mutex-lock!", fun: thread::mutex_lock, arity: 1, variadic: false },
+    Descr { name: "mutex-unlock!", fun: thread::mutex_unlock, arity: 1, variadic: true },
+    Descr { name: "make-condition-variable", fun: thread::make_condition_variable,
+            arity: 0, variadic: true },
+    Descr { name: "condition-variable?", fun: thread::is_condition_variable,
+            arity: 1, variadic: false },
+    Descr { name: "condition-variable-signal!", fun: thread::condition_variable_signal,
+            arity: 1, variadic: false },
+    Descr { name: "condition-variable-broadcast!", fun: thread::condition_variable_broadcast,
+            arity: 1, variadic: false },
+
+    /* processes */
+    Descr { name: "spawn", fun: process::spawn, arity: 1, variadic: false },
+    Descr { name: "mailbox?", fun: process::is_mailbox, arity: 1, variadic: false },
+    Descr { name: "current-mailbox", fun: process::current_mailbox,
+            arity: 0, variadic: false },
+    Descr { name: "parent-mailbox", fun: process::parent_mailbox,
+            arity: 0, variadic: false },
+    Descr { name: "send", fun: process::send, arity: 2, variadic: false },
+    Descr { name: "receive", fun: process::receive, arity: 0, variadic: true },
+
+    /* backtraces */
+    Descr { name: "condition-backtrace", fun: condition::condition_backtrace,
+            arity: 1, variadic: false },
+
+    /* rationals */
+    Descr { name: "numerator", fun: arith::numerator, arity: 1, variadic: false },
+    Descr { name: "denominator", fun: arith::denominator, arity: 1, variadic: false },
+    Descr { name: "exact-integer?", fun: types::exact_integer, arity: 1, variadic: false },
+    Descr { name: "rational?", fun: types::rational, arity: 1, variadic: false },
+
+    /* flonums */
+    Descr { name: "exact->inexact", fun: arith::inexact, arity: 1, variadic: false },
+    Descr { name: "inexact", fun: arith::inexact, arity: 1, variadic: false },
+    Descr { name: "exact", fun: arith::exact, arity: 1, variadic: false },
+    Descr { name: "floor", fun: arith::floor, arity: 1, variadic: false },
+    Descr { name: "ceiling", fun: arith::ceiling, arity: 1, variadic: false },
+    Descr { name: "round", fun: arith::round, arity: 1, variadic: false },
+    Descr { name: "truncate", fun: arith::truncate, arity: 1, variadic: false },
+    Descr { name: "sqrt", fun: math::sqrt, arity: 1, variadic: false },
+    Descr { name: "exp", fun: math::exp, arity: 1, variadic: false },
+    Descr { name: "log", fun: math::log, arity: 1, variadic: true },
+    Descr { name: "sin", fun: math::sin, arity: 1, variadic: false },
+    Descr { name: "cos", fun: math::cos, arity: 1, variadic: false },
+    Descr { name: "tan", fun: math::tan, arity: 1, variadic: false },
+    Descr { name: "atan", fun: math::atan, arity: 1, variadic: true },
+    Descr { name: "nan?", fun: math::is_nan, arity: 1, variadic: false },
+    Descr { name: "infinite?", fun: math::is_infinite, arity: 1, variadic: false },
+
+    /* comparisons and integer arithmetic */
+    Descr { name: "<", fun: arith::lt, arity: 1, variadic: true },
+    Descr { name: ">", fun: arith::gt, arity: 1, variadic: true },
+    Descr { name: "<=", fun: arith::le, arity: 1, variadic: true },
+    Descr { name: ">=", fun: arith::ge, arity: 1, variadic: true },
+    Descr { name: "quotient", fun: arith::quotient, arity: 2, variadic: false },
+    Descr { name: "remainder", fun: arith::remainder, arity: 2, variadic: false },
+    Descr { name: "modulo", fun: arith::modulo, arity: 2, variadic: false },
+    Descr { name: "floor/", fun: arith::floor_div, arity: 2, variadic: false },
+    Descr { name: "truncate/", fun: arith::truncate_div, arity: 2, variadic: false },
+    Descr { name: "floor-quotient", fun: arith::floor_quotient,
+            arity: 2, variadic: false },
+    Descr { name: "floor-remainder", fun: arith::floor_remainder,
+            arity: 2, variadic: false },
+    Descr { name: "truncate-quotient", fun: arith::truncate_quotient,
+            arity: 2, variadic: false },
+    Descr { name: "truncate-remainder", fun: arith::truncate_remainder,
+            arity: 2, variadic: false },
+    Descr { name: "abs", fun: arith::abs, arity: 1, variadic: false },
+    Descr { name: "min", fun: arith::min, arity: 1, variadic: true },
+    Descr { name: "max", fun: arith::max, arity: 1, variadic: true },
+    Descr { name: "gcd", fun: arith::gcd, arity: 0, variadic: true },
+    Descr { name: "lcm", fun: arith::lcm, arity: 0, variadic: true },
+    Descr { name: "expt", fun: arith::expt, arity: 2, variadic: false },
+    Descr { name: "exact-integer-sqrt", fun: arith::exact_integer_sqrt,
+            arity: 1, variadic: false },
+    Descr { name: "zero?", fun: arith::is_zero, arity: 1, variadic: false },
+    Descr { name: "positive?", fun: arith::is_positive, arity: 1, variadic: false },
+    Descr { name: "negative?", fun: arith::is_negative, arity: 1, variadic: false },
+    Descr { name: "odd?", fun: arith::is_odd, arity: 1, variadic: false },
+    Descr { name: "even?", fun: arith::is_even, arity: 1, variadic: false },
+    Descr { name: "square", fun: arith::square, arity: 1, variadic: false },
+
+    /* numbers and strings */
+    Descr { name: "number->string", fun: convert::number_to_string,
+            arity: 1, variadic: true },
+    Descr { name: "string->number", fun: convert::string_to_number,
+            arity: 1, variadic: true },
+
+    /* bitwise operations */
+    Descr { name: "bitwise-and", fun: bitwise::bitwise_and, arity: 0, variadic: true },
+    Descr { name: "bitwise-or", fun: bitwise::bitwise_or, arity: 0, variadic: true },
+    Descr { name: "bitwise-xor", fun: bitwise::bitwise_xor, arity: 0, variadic: true },
+    Descr { name: "bitwise-not", fun: bitwise::bitwise_not, arity: 1, variadic: false },
+    Descr { name: "arithmetic-shift", fun: bitwise::arithmetic_shift,
+            arity: 2, variadic: false },
+    Descr { name: "bit-count", fun: bitwise::bit_count, arity: 1, variadic: false },
+    Descr { name: "integer-length", fun: bitwise::integer_length,
+            arity: 1, variadic: false },
+    Descr { name: "bit-set?", fun: bitwise::bit_set, arity: 2, variadic: false },
+    Descr { name: "copy-bit", fun: bitwise::copy_bit, arity: 3, variadic: false },
+
+    /* characters */
+    Descr { name: "char?", fun: char::is_char_value, arity: 1, variadic: false },
+    Descr { name: "char->integer", fun: char::char_to_integer, arity: 1, variadic: false },
+    Descr { name: "integer->char", fun: char::integer_to_char, arity: 1, variadic: false },
+    Descr { name: "char=?", fun: char::char_eq, arity: 1, variadic: true },
+    Descr { name: "char<?", fun: char::char_lt, arity: 1, variadic: true },
+    Descr { name: "char>?", fun: char::char_gt, arity: 1, variadic: true },
+    Descr { name: "char<=?", fun: char::char_le, arity: 1, variadic: true },
+    Descr { name: "char>=?", fun: char::char_ge, arity: 1, variadic: true },
+    Descr { name: "char-ci=?", fun: char::char_ci_eq, arity: 1, variadic: true },
+    Descr { name: "char-ci<?", fun: char::char_ci_lt, arity: 1, variadic: true },
+    Descr { name: "char-ci>?", fun: char::char_ci_gt, arity: 1, variadic: true },
+    Descr { name: "char-ci<=?", fun: char::char_ci_le, arity: 1, variadic: true },
+    Descr { name: "char-ci>=?", fun: char::char_ci_ge, arity: 1, variadic: true },
+    Descr { name: "char-upcase", fun: char::char_upcase, arity: 1, variadic: false },
+    Descr { name: "char-downcase", fun: char::char_downcase, arity: 1, variadic: false },
+    Descr { name: "char-foldcase", fun: char::char_foldcase, arity: 1, variadic: false },
+    Descr { name: "char-alphabetic?", fun: char::char_alphabetic,
+            arity: 1, variadic: false },
+    Descr { name: "char-numeric?", fun: char::char_numeric, arity: 1, variadic: false },
+    Descr { name: "char-whitespace?", fun: char::char_whitespace,
+            arity: 1, variadic: false },
+
+    /* strings */
+    Descr { name: "string?", fun: string::is_string_value, arity: 1, variadic: false },
+    Descr { name: "make-string", fun: string::make_string, arity: 1, variadic: true },
+    Descr { name: "string-length", fun: string::string_length, arity: 1, variadic: false },
+    Descr { name: "string-ref", fun: string::string_ref, arity: 2, variadic: false },
+    Descr { name: "string-set!", fun: string::string_set, arity: 3, variadic: false },
+    Descr { name: "substring", fun: string::substring, arity: 3, variadic: false },
+    Descr { name: "string-append", fun: string::string_append, arity: 0, variadic: true },
+    Descr { name: "string-copy", fun: string::substring, arity: 1, variadic: true },
+    Descr { name: "string->list", fun: string::string_to_list, arity: 1, variadic: true },
+    Descr { name: "list->string", fun: string::list_to_string, arity: 1, variadic: false },
+    Descr { name: "string=?", fun: string::string_eq, arity: 1, variadic: true },
+    Descr { name: "string<?", fun: string::string_lt, arity: 1, variadic: true },
+    Descr { name: "string>?", fun: string::string_gt, arity: 1, variadic: true },
+    Descr { name: "string<=?", fun: string::string_le, arity: 1, variadic: true },
+    Descr { name: "string>=?", fun: string::string_ge, arity: 1, variadic: true },
+    Descr { name: "string-upcase", fun: string::string_upcase, arity: 1, variadic: false },
+    Descr { name: "string-downcase", fun: string::string_downcase,
+            arity: 1, variadic: false },
+
+    /* vectors */
+    Descr { name: "vector?", fun: vector::is_vector_value, arity: 1, variadic: false },
+    Descr { name: "make-vector", fun: vector::make_vector, arity: 1, variadic: true },
+    Descr { name: "vector", fun: vector::vector, arity: 0, variadic: true },
+    Descr { name: "vector-length", fun: vector::vector_length, arity: 1, variadic: false },
+    Descr { name: "vector-ref", fun: vector::vector_ref, arity: 2, variadic: false },
+    Descr { name: "vector-set!", fun: vector::vector_set, arity: 3, variadic: false },
+    Descr { name: "vector->list", fun: vector::vector_to_list, arity: 1, variadic: true },
+    Descr { name: "list->vector", fun: vector::list_to_vector, arity: 1, variadic: false },
+    Descr { name: "vector-fill!", fun: vector::vector_fill, arity: 2, variadic: true },
+    Descr { name: "vector-copy", fun: vector::vector_copy, arity: 1, variadic: true },
+    Descr { name: "vector-map", fun: vector::vector_map, arity: 2, variadic: false },
+    Descr { name: "vector-for-each", fun: vector::vector_for_each,
+            arity: 2, variadic: false },
+
+    /* bytevectors */
+    Descr { name: "bytevector?", fun: bytevector::is_bytevector_value,
+            arity: 1, variadic: false },
+    Descr { name: "make-bytevector", fun: bytevector::make_bytevector,
+            arity: 1, variadic: true },
+    Descr { name: "bytevector", fun: bytevector::bytevector, arity: 0, variadic: true },
+    Descr { name: "bytevector-length", fun: bytevector::bytevector_length,
+            arity: 1, variadic: false },
+    Descr { name: "bytevector-u8-ref", fun: bytevector::bytevector_u8_ref,
+            arity: 2, variadic: false },
+    Descr { name: "bytevector-u8-set!", fun: bytevector::bytevector_u8_set,
+            arity: 3, variadic: false },
+    Descr { name: "bytevector-copy", fun: bytevector::bytevector_copy,
+            arity: 1, variadic: true },
+    Descr { name: "bytevector-copy!", fun: bytevector::bytevector_copy_to,
+            arity: 3, variadic: true },
+    Descr { name: "bytevector-append", fun: bytevector::bytevector_append,
+            arity: 0, variadic: true },
+    Descr { name: "utf8->string", fun: bytevector::utf8_to_string, arity: 1, variadic: true },
+    Descr { name: "string->utf8", fun: bytevector::string_to_utf8, arity: 1, variadic: true },
+
+    /* hash tables */
+    Descr { name: "eqv?", fun: boolean::eqv, arity: 2, variadic: false },
+    Descr { name: "hash-table?", fun: hashtable::is_hash_table_value,
+            arity: 1, variadic: false },
+    Descr { name: "make-hash-table", fun: hashtable::make_hash_table,
+            arity: 0, variadic: true },
+    Descr { name: "hash-table-ref", fun: hashtable::hash_table_ref, arity: 2, variadic: true },
+    Descr { name: "hash-table-ref/default", fun: hashtable::hash_table_ref_default,
+            arity: 3, variadic: false },
+    Descr { name: "hash-table-set!", fun: hashtable::hash_table_set,
+            arity: 3, variadic: false },
+    Descr { name: "hash-table-delete!", fun: hashtable::hash_table_delete,
+            arity: 2, variadic: false },
+    Descr { name: "hash-table-contains?", fun: hashtable::hash_table_contains,
+            arity: 2, variadic: false },
+    Descr { name: "hash-table-exists?", fun: hashtable::hash_table_contains,
+            arity: 2, variadic: false },
+    Descr { name: "hash-table-update!", fun: hashtable::hash_table_update,
+            arity: 3, variadic: true },
+    Descr { name: "hash-table-update!/default", fun: hashtable::hash_table_update_default,
+            arity: 4, variadic: false },
+    Descr { name: "hash-table-walk", fun: hashtable::hash_table_walk,
+            arity: 2, variadic: false },
+    Descr { name: "hash-table-count", fun: hashtable::hash_table_count,
+            arity: 1, variadic: false },
+    Descr { name: "hash-table-size", fun: hashtable::hash_table_count,
+            arity: 1, variadic: false },
+    Descr { name: "hash-table-keys", fun: hashtable::hash_table_keys,
+            arity: 1, variadic: false },
+    Descr { name: "hash-table-values", fun: hashtable::hash_table_values,
+            arity: 1, variadic: false },
+    Descr { name: "hash-table->alist", fun: hashtable::hash_table_to_alist,
+            arity: 1, variadic: false },
+
+    /* output */
+    Descr { name: "write", fun: display::write, arity: 1, variadic: false }
+];
+
+// the primitive of the given name, for programs linking primitives
+// by name
+pub fn lookup(name: &str) -> Option<&'static Descr> {
+    TABLE.iter().find(|d| d.name == name)
+}
+
 pub fn env(gc: &mut gc::GC) -> gc::Ptr<gc::Env> {
     use gc::value::Primitive;
-    gc.alloc(gc::Env {
-        values: vec!(
-            /* arith primitives */
-            (true, Primitive(arith::add, "+")),
-            (true, Primitive(arith::min, "-")),
-            (true, Primitive(arith::mul, "*")),
-            (true, Primitive(arith::div, "/")),
-
-            /* boolean primitives */
-            (true, Primitive(boolean::cmp, "=")),
-            (true, Primitive(boolean::eq, "eq?")),
-            (true, Primitive(boolean::equal, "equal?")),
-
-            /* type predicates */
-            (true, Primitive(types::boolean, "boolean?" )),
-            (true, Primitive(types::null, "null?")),
-            (true, Primitive(types::pair, "pair?")),
-            (true, Primitive(types::procedure, "procedure?")),
-            (true, Primitive(types::symbol, "symbol?")),
-            (true, Primitive(types::number, "number?")),
-
-            /* type converters */
-            (true, Primitive(convert::symbol_to_string, "symbol->string")),
-            (true, Primitive(convert::string_to_symbol, "string->symbol")),
-
-            /* pair utils */
-            (true, Primitive(pair::cons, "cons")),
-            (true, Primitive(pair::car, "car")),
-            (true, Primitive(pair::cdr, "cdr")),
-            (true, Primitive(pair::setcar, "set-car!")),
-            (true, Primitive(pair::setcdr, "set-cdr!")),
-
-            /* list utils */
-            (true, Primitive(list, "list")),
-            (true, Primitive(list::is_list, "list?")),
-            (true, Primitive(list::map, "map")),
-            (true, Primitive(list::filter, "filter")),
-
-            /* display */
-            (true, Primitive(display::display, "display")),
-            (true, Primitive(display::newline, "newline")),
-
-            /* misc */
-            (true, Primitive(control::exit, "exit")),
-            (true, Primitive(control::assert, "assert"))
-        ),
-        next: None
-    })
+
+    // the capacity of an environment is its size
+    let mut values = Vec::with_capacity(TABLE.len());
+    for d in TABLE.iter() {
+        values.push((true, Primitive(d.fun, d.name)));
+    }
+
+    gc.alloc(gc::Env { values: values, next: None })
 }
 
 pub struct Arguments<'a> {
@@ -108,3 +390,80 @@ impl<'a> ops::Index<u8> for Arguments<'a> {
         &self.vm.stack[idx]
     }
 }
+
+// helpers shared by the primitives, raising errors for bad arguments
+
+pub fn irritant(vm: &mut vm::VM, v: &gc::Value) -> gc::Value {
+    value::Pair(value::list::cons(v, &value::Null, &mut *vm.gc))
+}
+
+pub fn type_error(vm: &mut vm::VM, what: &str, v: &gc::Value) -> ! {
+    let irritants = irritant(vm, v);
+    vm.error("type", format!("expected {}", what), irritants)
+}
+
+// what is the kind of the argument, e.g. "index"
+pub fn range_error(vm: &mut vm::VM, what: &str, v: &gc::Value) -> ! {
+    let irritants = irritant(vm, v);
+    vm.error("range", format!("{} out of range", what), irritants)
+}
+
+// the arguments, raising a type error unless they all satisfy p
+pub fn args<'a>(argv: Arguments<'a>, p: fn(&gc::Value) -> bool,
+                what: &str) -> (Vec<gc::Value>, &'a mut vm::VM) {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    for v in args.iter() {
+        if !p(v) {
+            type_error(vm, what, v)
+        }
+    }
+
+    (args, vm)
+}
+
+pub fn index(vm: &mut vm::VM, v: &gc::Value) -> usize {
+    match v {
+        &value::Fixnum(i) if i >= 0 => i as usize,
+        v => type_error(vm, "an index", v)
+    }
+}
+
+// the largest strings, vectors and bytevectors that primitives make
+pub const MAX_LENGTH: usize = 1 << 24;
+
+// a length, raising a range error if it exceeds MAX_LENGTH
+pub fn length(vm: &mut vm::VM, v: &gc::Value) -> usize {
+    let k = index(vm, v);
+
+    if k > MAX_LENGTH {
+        range_error(vm, "length", v)
+    }
+
+    k
+}
+
+// the optional start and end indexes of the given arguments, checking
+// that start <= end <= len
+pub fn range(vm: &mut vm::VM, len: usize, bounds: &[gc::Value]) -> (usize, usize) {
+    let start = match bounds.get(0) {
+        Some(v) => index(vm, v),
+        None => 0
+    };
+
+    let end = match bounds.get(1) {
+        Some(v) => index(vm, v),
+        None => len
+    };
+
+    if start > len {
+        range_error(vm, "index", &bounds[0])
+    }
+
+    if end < start || end > len {
+        range_error(vm, "index", &bounds[1])
+    }
+
+    (start, end)
+}
@@ -0,0 +1,666 @@
+use std::cmp::Ordering;
+use std::f64;
+use std::u32;
+
+use gc;
+use gc::value;
+use gmp::mpq::Mpq;
+use gmp::mpz::Mpz;
+
+// Numbers
+// integers that fit in an i64 are immediate fixnums, the others are
+// bignums, and exact non-integers are rationals. Operations on fixnums
+// check for overflow and fall back to bignum arithmetic, and every result
+// is normalised, so that a number always has the same representation:
+// a rational never has a denominator of 1. Flonums are inexact, and
+// operations involving one give a flonum. The functions below return
+// None when given values that are not numbers.
+
+// the size in bits of the largest integers that expt and the bitwise
+// primitives make
+pub const MAX_BITS: u64 = 1 << 24;
+
+pub fn from_mpz(n: Mpz) -> gc::Value {
+    match Option::<i64>::from(&n) {
+        Some(i) => value::Fixnum(i),
+        None => value::Num(n)
+    }
+}
+
+pub fn from_mpq(q: Mpq) -> gc::Value {
+    if q.get_den() == Mpz::one() {
+        from_mpz(q.get_num())
+    } else {
+        value::Ratio(q)
+    }
+}
+
+// the value of an integer
+pub fn to_mpz(v: &gc::Value) -> Option<Mpz> {
+    match v {
+        &value::Fixnum(i) => Some(Mpz::from(i)),
+        &value::Num(ref n) => Some(n.clone()),
+        _ => None
+    }
+}
+
+// the value of an exact number
+pub fn to_mpq(v: &gc::Value) -> Option<Mpq> {
+    match v {
+        &value::Ratio(ref q) => Some(q.clone()),
+        v => to_mpz(v).map(Mpq::from)
+    }
+}
+
+pub fn to_f64(v: &gc::Value) -> Option<f64> {
+    match v {
+        &value::Fixnum(i) => Some(i as f64),
+        &value::Num(ref n) => Some(f64::from(n)),
+        &value::Ratio(ref q) => Some(f64::from(q)),
+        &value::Flonum(f) => Some(f),
+        _ => None
+    }
+}
+
+pub fn is_integer(v: &gc::Value) -> bool {
+    match v {
+        &value::Fixnum(_) | &value::Num(_) => true,
+        _ => false
+    }
+}
+
+pub fn is_exact(v: &gc::Value) -> bool {
+    match v {
+        &value::Ratio(_) => true,
+        v => is_integer(v)
+    }
+}
+
+pub fn is_number(v: &gc::Value) -> bool {
+    match v {
+        &value::Flonum(_) => true,
+        v => is_exact(v)
+    }
+}
+
+pub fn is_zero(v: &gc::Value) -> bool {
+    match v {
+        &value::Fixnum(0) => true,
+        _ => false
+    }
+}
+
+// the exact number equal to a number, None for infinities and NaNs
+pub fn exact(v: &gc::Value) -> Option<gc::Value> {
+    match v {
+        &value::Flonum(f) if f.is_finite() => {
+            let mut q = Mpq::new();
+            q.set_d(f);
+            Some(from_mpq(q))
+        }
+
+        v if is_exact(v) => Some(v.clone()),
+        _ => None
+    }
+}
+
+pub fn inexact(v: &gc::Value) -> Option<gc::Value> {
+    to_f64(v).map(value::Flonum)
+}
+
+// apply an operation to two numbers, on integers if both are, on
+// flonums if either is
+fn apply<Z, Q, F>(a: &gc::Value, b: &gc::Value, z: Z, q: Q, f: F) -> Option<gc::Value>
+    where Z: Fn(Mpz, Mpz) -> Mpz, Q: Fn(Mpq, Mpq) -> Mpq, F: Fn(f64, f64) -> f64 {
+    match (a, b) {
+        (&value::Flonum(_), _) | (_, &value::Flonum(_)) => match (to_f64(a), to_f64(b)) {
+            (Some(x), Some(y)) => Some(value::Flonum(f(x, y))),
+            _ => None
+        },
+
+        _ => match (to_mpz(a), to_mpz(b)) {
+            (Some(i), Some(j)) => Some(from_mpz(z(i, j))),
+            _ => match (to_mpq(a), to_mpq(b)) {
+                (Some(i), Some(j)) => Some(from_mpq(q(i, j))),
+                _ => None
+            }
+        }
+    }
+}
+
+pub fn add(a: &gc::Value, b: &gc::Value) -> Option<gc::Value> {
+    match (a, b) {
+        (&value::Fixnum(i), &value::Fixnum(j)) => match i.checked_add(j) {
+            Some(r) => Some(value::Fixnum(r)),
+            None => Some(from_mpz(Mpz::from(i) + Mpz::from(j)))
+        },
+
+        _ => apply(a, b, |i, j| i + j, |i, j| i + j, |x, y| x + y)
+    }
+}
+
+pub fn sub(a: &gc::Value, b: &gc::Value) -> Option<gc::Value> {
+    match (a, b) {
+        (&value::Fixnum(i), &value::Fixnum(j)) => match i.checked_sub(j) {
+            Some(r) => Some(value::Fixnum(r)),
+            None => Some(from_mpz(Mpz::from(i) - Mpz::from(j)))
+        },
+
+        _ => apply(a, b, |i, j| i - j, |i, j| i - j, |x, y| x - y)
+    }
+}
+
+pub fn mul(a: &gc::Value, b: &gc::Value) -> Option<gc::Value> {
+    match (a, b) {
+        (&value::Fixnum(i), &value::Fixnum(j)) => match i.checked_mul(j) {
+            Some(r) => Some(value::Fixnum(r)),
+            None => Some(from_mpz(Mpz::from(i) * Mpz::from(j)))
+        },
+
+        _ => apply(a, b, |i, j| i * j, |i, j| i * j, |x, y| x * y)
+    }
+}
+
+pub fn neg(a: &gc::Value) -> Option<gc::Value> {
+    match a {
+        &value::Flonum(f) => Some(value::Flonum(-f)),
+        a => sub(&value::Fixnum(0), a)
+    }
+}
+
+// b must not be an exact zero
+pub fn div(a: &gc::Value, b: &gc::Value) -> Option<gc::Value> {
+    match (a, b) {
+        (&value::Fixnum(i), &value::Fixnum(j)) if i.checked_rem(j) == Some(0) => {
+            i.checked_div(j).map(value::Fixnum)
+                .or_else(|| Some(from_mpz(Mpz::from(i) / Mpz::from(j))))
+        }
+
+        (&value::Flonum(_), _) | (_, &value::Flonum(_)) => match (to_f64(a), to_f64(b)) {
+            (Some(x), Some(y)) => Some(value::Flonum(x / y)),
+            _ => None
+        },
+
+        _ => match (to_mpq(a), to_mpq(b)) {
+            (Some(i), Some(j)) => Some(from_mpq(i / j)),
+            _ => None
+        }
+    }
+}
+
+// the numerator and denominator of rationals, those of flonums are
+// computed on their exact value
+pub fn numerator(a: &gc::Value) -> Option<gc::Value> {
+    match a {
+        &value::Ratio(ref q) => Some(from_mpz(q.get_num())),
+        &value::Flonum(_) => exact(a).and_then(|q| numerator(&q)).and_then(|n| inexact(&n)),
+        a if is_integer(a) => Some(a.clone()),
+        _ => None
+    }
+}
+
+pub fn denominator(a: &gc::Value) -> Option<gc::Value> {
+    match a {
+        &value::Ratio(ref q) => Some(from_mpz(q.get_den())),
+        &value::Flonum(_) => exact(a).and_then(|q| denominator(&q)).and_then(|d| inexact(&d)),
+        a if is_integer(a) => Some(value::Fixnum(1)),
+        _ => None
+    }
+}
+
+// halfway cases are rounded to the even integer
+fn round_mpq(q: &Mpq) -> Mpz {
+    let half = Mpq::ratio(&Mpz::one(), &Mpz::from(2));
+    let r = (q + &half).floor();
+
+    if q.get_den() == Mpz::from(2) && r.tstbit(0) {
+        r - Mpz::one()
+    } else {
+        r
+    }
+}
+
+fn round_f64(f: f64) -> f64 {
+    if (f - f.trunc()).abs() == 0.5 {
+        2.0 * (f / 2.0).round()
+    } else {
+        f.round()
+    }
+}
+
+pub fn floor(a: &gc::Value) -> Option<gc::Value> {
+    match a {
+        &value::Ratio(ref q) => Some(from_mpz(q.floor())),
+        &value::Flonum(f) => Some(value::Flonum(f.floor())),
+        a if is_integer(a) => Some(a.clone()),
+        _ => None
+    }
+}
+
+pub fn ceiling(a: &gc::Value) -> Option<gc::Value> {
+    match a {
+        &value::Ratio(ref q) => Some(from_mpz(q.ceil())),
+        &value::Flonum(f) => Some(value::Flonum(f.ceil())),
+        a if is_integer(a) => Some(a.clone()),
+        _ => None
+    }
+}
+
+pub fn round(a: &gc::Value) -> Option<gc::Value> {
+    match a {
+        &value::Ratio(ref q) => Some(from_mpz(round_mpq(q))),
+        &value::Flonum(f) => Some(value::Flonum(round_f64(f))),
+        a if is_integer(a) => Some(a.clone()),
+        _ => None
+    }
+}
+
+pub fn truncate(a: &gc::Value) -> Option<gc::Value> {
+    match a {
+        &value::Ratio(ref q) if *q < Mpq::zero() => Some(from_mpz(q.ceil())),
+        &value::Ratio(ref q) => Some(from_mpz(q.floor())),
+        &value::Flonum(f) => Some(value::Flonum(f.trunc())),
+        a if is_integer(a) => Some(a.clone()),
+        _ => None
+    }
+}
+
+// the square root, exact for the squares of exact numbers
+pub fn sqrt(a: &gc::Value) -> Option<gc::Value> {
+    let exact_root = match to_mpq(a) {
+        Some(ref q) if *q >= Mpq::zero() => {
+            let (n, d) = (q.get_num(), q.get_den());
+            let (rn, rd) = (n.sqrt(), d.sqrt());
+
+            if &rn * &rn == n && &rd * &rd == d {
+                Some(from_mpq(Mpq::ratio(&rn, &rd)))
+            } else {
+                None
+            }
+        }
+
+        _ => None
+    };
+
+    exact_root.or_else(|| to_f64(a).map(|f| value::Flonum(f.sqrt())))
+}
+
+// numeric equality
+pub fn eq(a: &gc::Value, b: &gc::Value) -> Option<bool> {
+    match (a, b) {
+        (&value::Fixnum(i), &value::Fixnum(j)) => Some(i == j),
+        (&value::Num(ref i), &value::Num(ref j)) => Some(i == j),
+        (&value::Ratio(ref i), &value::Ratio(ref j)) => Some(i == j),
+        (&value::Flonum(x), &value::Flonum(y)) => Some(x == y),
+        // flonums are compared with exact numbers exactly
+        (&value::Flonum(_), b) if is_exact(b) => Some(exact(a).as_ref() == Some(b)),
+        (a, &value::Flonum(_)) if is_exact(a) => Some(exact(b).as_ref() == Some(a)),
+        // normalised exact numbers of different representations differ
+        (a, b) if is_exact(a) && is_exact(b) => Some(false),
+        _ => None
+    }
+}
+
+// numeric ordering, None if either is not a number or is a NaN
+pub fn cmp(a: &gc::Value, b: &gc::Value) -> Option<Ordering> {
+    match (a, b) {
+        (&value::Fixnum(i), &value::Fixnum(j)) => Some(i.cmp(&j)),
+        (&value::Flonum(x), &value::Flonum(y)) => x.partial_cmp(&y),
+        (&value::Flonum(x), b) => cmp_flonum(x, b),
+        (a, &value::Flonum(y)) => cmp_flonum(y, a).map(Ordering::reverse),
+        _ => match (to_mpq(a), to_mpq(b)) {
+            (Some(i), Some(j)) => Some(i.cmp(&j)),
+            _ => None
+        }
+    }
+}
+
+// flonums are compared with exact numbers exactly
+fn cmp_flonum(x: f64, b: &gc::Value) -> Option<Ordering> {
+    if x.is_nan() || !is_exact(b) {
+        None
+    } else if x == f64::INFINITY {
+        Some(Ordering::Greater)
+    } else if x == f64::NEG_INFINITY {
+        Some(Ordering::Less)
+    } else {
+        match (exact(&value::Flonum(x)).and_then(|e| to_mpq(&e)), to_mpq(b)) {
+            (Some(p), Some(q)) => Some(p.cmp(&q)),
+            _ => None
+        }
+    }
+}
+
+pub fn abs(a: &gc::Value) -> Option<gc::Value> {
+    match a {
+        &value::Flonum(f) => Some(value::Flonum(f.abs())),
+        a => match cmp(a, &value::Fixnum(0)) {
+            Some(Ordering::Less) => neg(a),
+            Some(_) => Some(a.clone()),
+            None => None
+        }
+    }
+}
+
+fn integers<F>(a: &gc::Value, b: &gc::Value, f: F) -> Option<gc::Value>
+    where F: Fn(&Mpz, &Mpz) -> Mpz {
+    match (to_mpz(a), to_mpz(b)) {
+        (Some(i), Some(j)) => Some(from_mpz(f(&i, &j))),
+        _ => None
+    }
+}
+
+// integer divisions, b must not be zero
+pub fn quotient(a: &gc::Value, b: &gc::Value) -> Option<gc::Value> {
+    integers(a, b, |i, j| i / j)
+}
+
+pub fn remainder(a: &gc::Value, b: &gc::Value) -> Option<gc::Value> {
+    integers(a, b, |i, j| i % j)
+}
+
+pub fn floor_quotient(a: &gc::Value, b: &gc::Value) -> Option<gc::Value> {
+    integers(a, b, |i, j| i.div_floor(j))
+}
+
+pub fn modulo(a: &gc::Value, b: &gc::Value) -> Option<gc::Value> {
+    integers(a, b, |i, j| i.mod_floor(j))
+}
+
+pub fn gcd(a: &gc::Value, b: &gc::Value) -> Option<gc::Value> {
+    integers(a, b, |i, j| i.gcd(j))
+}
+
+pub fn lcm(a: &gc::Value, b: &gc::Value) -> Option<gc::Value> {
+    integers(a, b, |i, j| i.lcm(j))
+}
+
+// bitwise operations, on the two's complement representation of integers
+pub fn bit_and(a: &gc::Value, b: &gc::Value) -> Option<gc::Value> {
+    match (a, b) {
+        (&value::Fixnum(i), &value::Fixnum(j)) => Some(value::Fixnum(i & j)),
+        _ => integers(a, b, |i, j| i & j)
+    }
+}
+
+pub fn bit_or(a: &gc::Value, b: &gc::Value) -> Option<gc::Value> {
+    match (a, b) {
+        (&value::Fixnum(i), &value::Fixnum(j)) => Some(value::Fixnum(i | j)),
+        _ => integers(a, b, |i, j| i | j)
+    }
+}
+
+pub fn bit_xor(a: &gc::Value, b: &gc::Value) -> Option<gc::Value> {
+    match (a, b) {
+        (&value::Fixnum(i), &value::Fixnum(j)) => Some(value::Fixnum(i ^ j)),
+        _ => integers(a, b, |i, j| i ^ j)
+    }
+}
+
+pub fn bit_not(a: &gc::Value) -> Option<gc::Value> {
+    match a {
+        &value::Fixnum(i) => Some(value::Fixnum(!i)),
+        a => to_mpz(a).map(|n| from_mpz(n.compl()))
+    }
+}
+
+// shift to the left, or to the right rounding down for negative counts
+pub fn arithmetic_shift(a: &gc::Value, count: i64) -> Option<gc::Value> {
+    to_mpz(a).map(|n| if count >= 0 {
+        from_mpz(&n << count as usize)
+    } else {
+        from_mpz(&n >> count.wrapping_neg() as u64 as usize)
+    })
+}
+
+// the number of bits set in a positive integer, or cleared in a negative one
+pub fn bit_count(a: &gc::Value) -> Option<gc::Value> {
+    to_mpz(a).map(|n| if n < Mpz::zero() {
+        value::Fixnum(n.compl().popcount() as i64)
+    } else {
+        value::Fixnum(n.popcount() as i64)
+    })
+}
+
+// the number of bits needed to represent an integer, without the sign
+pub fn integer_length(a: &gc::Value) -> Option<gc::Value> {
+    to_mpz(a).map(|n| {
+        let m = if n < Mpz::zero() { n.compl() } else { n };
+
+        if m.is_zero() {
+            value::Fixnum(0)
+        } else {
+            value::Fixnum(m.bit_length() as i64)
+        }
+    })
+}
+
+pub fn bit_set(index: usize, a: &gc::Value) -> Option<bool> {
+    to_mpz(a).map(|n| n.tstbit(index))
+}
+
+pub fn copy_bit(index: usize, a: &gc::Value, bit: bool) -> Option<gc::Value> {
+    to_mpz(a).map(|mut n| {
+        if bit {
+            n.setbit(index);
+        } else {
+            n.clrbit(index);
+        }
+
+        from_mpz(n)
+    })
+}
+
+// exact for exact bases and integer exponents that fit in an u32, where
+// a negative exponent requires a base other than zero. Inexact otherwise
+pub fn expt(a: &gc::Value, b: &gc::Value) -> Option<gc::Value> {
+    match exact_expt(a, b) {
+        Some((q, e)) => {
+            let p = e.abs() as u32;
+            let r = Mpq::ratio(&q.get_num().pow(p), &q.get_den().pow(p));
+            Some(from_mpq(if e < 0 { Mpq::one() / r } else { r }))
+        }
+
+        None => match (to_f64(a), to_f64(b)) {
+            (Some(x), Some(y)) => Some(value::Flonum(x.powf(y))),
+            _ => None
+        }
+    }
+}
+
+// the base and the exponent of expt when its result is exact
+fn exact_expt(a: &gc::Value, b: &gc::Value) -> Option<(Mpq, i64)> {
+    let max = u32::MAX as i64;
+
+    match (to_mpq(a), to_mpz(b).and_then(|e| Option::<i64>::from(&e))) {
+        (Some(q), Some(e)) if e >= -max && e <= max => Some((q, e)),
+        _ => None
+    }
+}
+
+// an upper bound of the number of bits of the numerator and denominator
+// of the result of expt, None if the result is inexact
+pub fn expt_bits(a: &gc::Value, b: &gc::Value) -> Option<u64> {
+    exact_expt(a, b).map(|(q, e)| {
+        let bits = |n: Mpz| if n.abs() <= Mpz::one() {
+            1
+        } else {
+            (n.bit_length() as u64).saturating_mul(e.abs() as u64)
+        };
+
+        ::std::cmp::max(bits(q.get_num()), bits(q.get_den()))
+    })
+}
+
+// the root s and the rest r of a positive integer n, n = s * s + r
+pub fn exact_integer_sqrt(a: &gc::Value) -> Option<(gc::Value, gc::Value)> {
+    match to_mpz(a) {
+        Some(ref n) if *n >= Mpz::zero() => {
+            let s = n.sqrt();
+            let r = n - &s * &s;
+            Some((from_mpz(s), from_mpz(r)))
+        }
+
+        _ => None
+    }
+}
+
+// the external representation of a flonum, the shortest one that reads
+// back as the same number
+pub fn flonum_to_string(f: f64) -> String {
+    if f.is_nan() {
+        return "+nan.0".to_string()
+    } else if f == f64::INFINITY {
+        return "+inf.0".to_string()
+    } else if f == f64::NEG_INFINITY {
+        return "-inf.0".to_string()
+    }
+
+    let s = format!("{:?}", f);
+
+    if s.contains('.') || s.contains('e') {
+        s
+    } else {
+        s + ".0"
+    }
+}
+
+// the representation of a number in the given radix, None for flonums
+// in a radix other than 10
+pub fn to_string_radix(v: &gc::Value, radix: u8) -> Option<String> {
+    match v {
+        &value::Fixnum(i) => Some(Mpz::from(i).to_str_radix(radix)),
+        &value::Num(ref n) => Some(n.to_str_radix(radix)),
+        &value::Ratio(ref q) => Some(format!("{}/{}", q.get_num().to_str_radix(radix),
+                                             q.get_den().to_str_radix(radix))),
+        &value::Flonum(f) if radix == 10 => Some(flonum_to_string(f)),
+        _ => None
+    }
+}
+
+fn parse_uint(s: &str, radix: u32) -> Option<Mpz> {
+    if s.is_empty() || !s.chars().all(|c| c.is_digit(radix)) {
+        return None
+    }
+
+    Mpz::from_str_radix(s, radix as u8).ok()
+}
+
+// an unsigned decimal such as 1.5, .5, or 15e-1, as the digits without
+// the point and the power of 10 they are multiplied by
+fn parse_decimal(s: &str) -> Option<(Mpz, i64)> {
+    let (mantissa, exp) = match s.find(|c| c == 'e' || c == 'E') {
+        Some(i) => {
+            let e = &s[i + 1 ..];
+            let digits = e.trim_left_matches(|c| c == '+' || c == '-');
+
+            if e.len() - digits.len() > 1 || !digits.chars().all(|c| c.is_digit(10)) {
+                return None
+            }
+
+            match e.parse::<i32>() {
+                Ok(e) => (&s[.. i], e as i64),
+                Err(_) => return None
+            }
+        }
+
+        None => (s, 0)
+    };
+
+    let (int, frac) = match mantissa.find('.') {
+        Some(i) => (&mantissa[.. i], &mantissa[i + 1 ..]),
+        None => (mantissa, "")
+    };
+
+    if int.is_empty() && frac.is_empty() {
+        return None
+    }
+
+    let digits = format!("{}{}", int, frac);
+    parse_uint(&digits, 10).map(|n| (n, exp - frac.len() as i64))
+}
+
+// the largest power of 10 by which an exact decimal is scaled
+const MAX_EXACT_EXPONENT: i64 = 1000000;
+
+// read a number, with the syntax of R7RS: optional radix and exactness
+// prefixes, a sign, then an integer, a rational, a decimal in radix 10,
+// or an infinity or a NaN
+pub fn parse(s: &str, radix: u32) -> Option<gc::Value> {
+    let mut s = s;
+    let mut radix_prefix = None;
+    let mut exactness = None;
+
+    while s.starts_with("#") {
+        match s[1 ..].chars().next().map(|c| c.to_ascii_lowercase()) {
+            Some('x') if radix_prefix.is_none() => radix_prefix = Some(16),
+            Some('b') if radix_prefix.is_none() => radix_prefix = Some(2),
+            Some('o') if radix_prefix.is_none() => radix_prefix = Some(8),
+            Some('d') if radix_prefix.is_none() => radix_prefix = Some(10),
+            Some('e') if exactness.is_none() => exactness = Some(true),
+            Some('i') if exactness.is_none() => exactness = Some(false),
+            _ => return None
+        }
+
+        s = &s[2 ..];
+    }
+
+    let radix = radix_prefix.unwrap_or(radix);
+    let (negative, signed, body) = match s.chars().next() {
+        Some('+') => (false, true, &s[1 ..]),
+        Some('-') => (true, true, &s[1 ..]),
+        _ => (false, false, s)
+    };
+
+    let exact_value = match &body.to_ascii_lowercase()[..] {
+        "inf.0" if signed && exactness != Some(true) => {
+            return Some(value::Flonum(if negative { f64::NEG_INFINITY } else { f64::INFINITY }))
+        }
+
+        "nan.0" if signed && exactness != Some(true) => return Some(value::Flonum(f64::NAN)),
+        _ if body.contains('/') => {
+            let i = body.find('/').unwrap();
+            match (parse_uint(&body[.. i], radix), parse_uint(&body[i + 1 ..], radix)) {
+                (Some(_), Some(ref d)) if *d == Mpz::zero() => return None,
+                (Some(ref n), Some(ref d)) => from_mpq(Mpq::ratio(n, d)),
+                _ => return None
+            }
+        }
+
+        _ if radix == 10 && body.contains(|c| c == '.' || c == 'e' || c == 'E') => {
+            let (digits, exp) = match parse_decimal(body) {
+                Some(d) => d,
+                None => return None
+            };
+
+            if exactness != Some(true) {
+                // the standard library rounds decimals correctly
+                return s.parse::<f64>().ok().map(value::Flonum)
+            }
+
+            if exp.abs() > MAX_EXACT_EXPONENT {
+                return None
+            }
+
+            let scale = Mpz::from(10).pow(exp.abs() as u32);
+            if exp < 0 {
+                from_mpq(Mpq::ratio(&digits, &scale))
+            } else {
+                from_mpz(digits * scale)
+            }
+        }
+
+        _ => match parse_uint(body, radix) {
+            Some(n) => from_mpz(n),
+            None => return None
+        }
+    };
+
+    let v = if negative { neg(&exact_value).unwrap() } else { exact_value };
+
+    if exactness == Some(false) {
+        inexact(&v)
+    } else {
+        Some(v)
+    }
+}
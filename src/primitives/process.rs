@@ -0,0 +1,121 @@
+use std::sync::mpsc;
+use std::time::Duration;
+
+use gc;
+use gc::value;
+use gmp::mpz::Mpz;
+use primitives::range_error;
+use primitives::num;
+use vm::process::Mailbox;
+use vm::process::Message;
+
+// Message passing between isolated processes
+// receiving blocks the whole VM, including its green threads
+
+pub fn spawn(argv: super::Arguments) -> gc::Value {
+    let prog = match argv.vec() {
+        [value::String(ref s)] => s.str.clone(),
+        [_] => panic!("Argument is not a string"),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    let tx = argv.vm.spawn(prog);
+    value::Mailbox(argv.vm.gc.alloc(Mailbox { tx: tx }))
+}
+
+pub fn is_mailbox(argv: super::Arguments) -> gc::Value {
+    match argv.vec() {
+        [value::Mailbox(_)] => value::Bool(true),
+        [_] => value::Bool(false),
+        _ => panic!("Bad arguments")
+    }
+}
+
+pub fn current_mailbox(argv: super::Arguments) -> gc::Value {
+    let tx = match argv.vec() {
+        [] => argv.vm.own_mailbox.clone(),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    value::Mailbox(argv.vm.gc.alloc(Mailbox { tx: tx }))
+}
+
+pub fn parent_mailbox(argv: super::Arguments) -> gc::Value {
+    let tx = match argv.vec() {
+        [] => argv.vm.parent_mailbox.clone(),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    match tx {
+        Some(tx) => value::Mailbox(argv.vm.gc.alloc(Mailbox { tx: tx })),
+        None => value::Bool(false)
+    }
+}
+
+pub fn send(argv: super::Arguments) -> gc::Value {
+    let (mb, msg) = match argv.vec() {
+        [value::Mailbox(ref mb), ref v] => (*mb, Message::from_value(v)),
+        [_, _] => panic!("Argument is not a mailbox"),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    let msg = match msg {
+        Ok(msg) => msg,
+        Err(v) => {
+            let irritants = value::Pair(value::list::cons(&v, &value::Null,
+                                                          &mut *argv.vm.gc));
+            argv.vm.error("process", "value cannot be sent".to_string(),
+                          irritants)
+        }
+    };
+
+    // the receiving process has terminated
+    if mb.tx.send(msg).is_err() {
+        argv.vm.error("process", "mailbox closed".to_string(), value::Null);
+    }
+
+    value::Unit
+}
+
+// (receive [timeout-ms [default]])
+// returns default, or #f, if no message arrived before the timeout
+
+pub fn receive(argv: super::Arguments) -> gc::Value {
+    let (timeout, default) = match argv.vec() {
+        [] => (None, value::Bool(false)),
+        [ref ms] if num::is_integer(ms) => (Some(ms.clone()), value::Bool(false)),
+        [ref ms, ref d] if num::is_integer(ms) => (Some(ms.clone()), d.clone()),
+        [_] | [_, _] => panic!("Timeout is not a number"),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    // a timeout too large for a u64 is as good as infinite
+    let timeout: Option<u64> = match timeout {
+        None => None,
+        Some(ms) => {
+            let n = num::to_mpz(&ms).unwrap();
+            if n < Mpz::zero() {
+                range_error(argv.vm, "timeout", &ms);
+            }
+
+            (&n).into()
+        }
+    };
+
+    let msg = match timeout {
+        None => argv.vm.mailbox.recv().map_err(|_| false),
+        Some(ms) => {
+            let timeout = Duration::from_millis(ms);
+            argv.vm.mailbox.recv_timeout(timeout).map_err(|e| {
+                e == mpsc::RecvTimeoutError::Timeout
+            })
+        }
+    };
+
+    match msg {
+        Ok(msg) => msg.to_value(&mut *argv.vm.gc),
+        Err(true) => default,
+        // unreachable, since the VM holds a handle on its own mailbox
+        Err(false) => panic!("Mailbox disconnected")
+    }
+}
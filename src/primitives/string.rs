@@ -0,0 +1,271 @@
+use gc;
+use gc::value;
+use gc::value::list;
+use primitives::args;
+use primitives::index;
+use primitives::irritant;
+use primitives::length;
+use primitives::range_error;
+use primitives::type_error;
+use vm;
+
+// Strings
+// strings are indexed by characters, not by bytes. Strings created by
+// programs are mutable, literals and the names of symbols are not
+
+fn is_string(v: &gc::Value) -> bool {
+    match v {
+        &value::String(_) => true,
+        _ => false
+    }
+}
+
+pub fn new_string(vm: &mut vm::VM, s: String) -> gc::Value {
+    value::String(vm.gc.alloc(gc::String { str: s, mutable: true }))
+}
+
+pub fn string<'a>(vm: &mut vm::VM, v: &'a gc::Value) -> &'a str {
+    match v {
+        &value::String(ref s) => &s.str,
+        v => type_error(vm, "a string", v)
+    }
+}
+
+fn character(vm: &mut vm::VM, v: &gc::Value) -> char {
+    match v {
+        &value::Char(c) => c,
+        v => type_error(vm, "a character", v)
+    }
+}
+
+// the byte offset of the k-th character of s, or of its end
+fn offset(s: &str, k: usize) -> Option<usize> {
+    s.char_indices().map(|(b, _)| b).chain(Some(s.len())).nth(k)
+}
+
+// the bytes of s between the optional start and end character indexes
+// of the given arguments, checking that start <= end <= length
+pub fn range(vm: &mut vm::VM, s: &str, bounds: &[gc::Value]) -> (usize, usize) {
+    let start = match bounds.get(0) {
+        Some(v) => index(vm, v),
+        None => 0
+    };
+
+    let end = match bounds.get(1) {
+        Some(v) => Some(index(vm, v)),
+        None => None
+    };
+
+    let b = offset(s, start);
+    let e = match end {
+        Some(end) if end >= start => offset(s, end),
+        Some(_) => None,
+        None => Some(s.len())
+    };
+
+    match (b, e) {
+        (Some(b), Some(e)) => (b, e),
+        (None, _) => range_error(vm, "index", &bounds[0]),
+        (_, None) => range_error(vm, "index", &bounds[1])
+    }
+}
+
+pub fn is_string_value(argv: super::Arguments) -> gc::Value {
+    match argv.vec() {
+        [ref v] => value::Bool(is_string(v)),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+// (make-string k [char])
+pub fn make_string(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    let (k, c) = match &args[..] {
+        [ref k] => (length(vm, k), ' '),
+        [ref k, ref c] => (length(vm, k), character(vm, c)),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    new_string(vm, ::std::iter::repeat(c).take(k).collect())
+}
+
+pub fn string_length(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref s] => value::Fixnum(string(vm, s).chars().count() as i64),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn string_ref(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref s, ref k] => {
+            let s = string(vm, s);
+            let k = index(vm, k);
+
+            match s.chars().nth(k) {
+                Some(c) => value::Char(c),
+                None => range_error(vm, "index", &args[1])
+            }
+        }
+
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn string_set(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [value::String(ref s), ref k, ref c] => {
+            let mut s = *s;
+
+            if !s.mutable {
+                let irritants = irritant(vm, &args[0]);
+                vm.error("string", "string is immutable".to_string(), irritants)
+            }
+
+            let c = character(vm, c);
+            let (b, old) = match s.str.char_indices().nth(index(vm, k)) {
+                Some(bc) => bc,
+                None => range_error(vm, "index", k)
+            };
+
+            let new = format!("{}{}{}", &s.str[.. b], c, &s.str[b + old.len_utf8() ..]);
+            s.str = new;
+        }
+
+        [ref s, _, _] => type_error(vm, "a string", s),
+        _ => panic!("Wrong number of arguments")
+    }
+
+    value::Unit
+}
+
+// (substring string start end), also (string-copy string [start [end]])
+pub fn substring(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref s, ref bounds ..] if bounds.len() <= 2 => {
+            let s = string(vm, s);
+            let (b, e) = range(vm, s, bounds);
+            new_string(vm, s[b .. e].to_string())
+        }
+
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn string_append(argv: super::Arguments) -> gc::Value {
+    let (args, vm) = args(argv, is_string, "a string");
+    let mut res = String::new();
+
+    for s in args.iter() {
+        res.push_str(string(vm, s));
+    }
+
+    new_string(vm, res)
+}
+
+// (string->list string [start [end]])
+pub fn string_to_list(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref s, ref bounds ..] if bounds.len() <= 2 => {
+            let s = string(vm, s);
+            let (b, e) = range(vm, s, bounds);
+            let mut ret = value::Null;
+
+            for c in s[b .. e].chars().rev() {
+                ret = value::Pair(list::cons(&value::Char(c), &ret, &mut *vm.gc));
+            }
+
+            ret
+        }
+
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn list_to_string(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    let chars: Vec<gc::Value> = match &args[..] {
+        [ref l] => list::iter(l, |_| panic!("Argument is not a list")).collect(),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    let mut res = String::new();
+    for c in chars.iter() {
+        res.push(character(vm, c));
+    }
+
+    new_string(vm, res)
+}
+
+// true if each string compares to the next with f
+fn compare(argv: super::Arguments, f: fn(&str, &str) -> bool) -> gc::Value {
+    let (args, vm) = args(argv, is_string, "a string");
+    let strs: Vec<&str> = args.iter().map(|s| string(vm, s)).collect();
+
+    value::Bool(strs.windows(2).all(|w| f(w[0], w[1])))
+}
+
+pub fn string_eq(argv: super::Arguments) -> gc::Value {
+    compare(argv, PartialEq::eq)
+}
+
+pub fn string_lt(argv: super::Arguments) -> gc::Value {
+    compare(argv, PartialOrd::lt)
+}
+
+pub fn string_gt(argv: super::Arguments) -> gc::Value {
+    compare(argv, PartialOrd::gt)
+}
+
+pub fn string_le(argv: super::Arguments) -> gc::Value {
+    compare(argv, PartialOrd::le)
+}
+
+pub fn string_ge(argv: super::Arguments) -> gc::Value {
+    compare(argv, PartialOrd::ge)
+}
+
+pub fn string_upcase(argv: super::Arguments) -> gc::Value {
+    let (args, vm) = args(argv, is_string, "a string");
+
+    match &args[..] {
+        [ref s] => {
+            let up = string(vm, s).to_uppercase();
+            new_string(vm, up)
+        }
+
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn string_downcase(argv: super::Arguments) -> gc::Value {
+    let (args, vm) = args(argv, is_string, "a string");
+
+    match &args[..] {
+        [ref s] => {
+            let down = string(vm, s).to_lowercase();
+            new_string(vm, down)
+        }
+
+        _ => panic!("Wrong number of arguments")
+    }
+}
@@ -0,0 +1,235 @@
+use gc;
+use gc::value;
+use primitives::irritant;
+use vm::thread;
+use vm::thread::State;
+
+// SRFI-18 threads, mutexes and condition variables
+// timeouts are not supported
+
+pub fn make_thread(argv: super::Arguments) -> gc::Value {
+    let (thunk, name) = match argv.vec() {
+        [ref thunk] => (thunk.clone(), value::Unit),
+        [ref thunk, ref name] => (thunk.clone(), name.clone()),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    let t = thread::Thread::new(thunk, name, State::New);
+    value::Thread(argv.vm.gc.alloc(t))
+}
+
+pub fn is_thread(argv: super::Arguments) -> gc::Value {
+    match argv.vec() {
+        [value::Thread(_)] => value::Bool(true),
+        [_] => value::Bool(false),
+        _ => panic!("Bad arguments")
+    }
+}
+
+pub fn current_thread(argv: super::Arguments) -> gc::Value {
+    match argv.vec() {
+        [] => value::Thread(argv.vm.thread),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn thread_name(argv: super::Arguments) -> gc::Value {
+    match argv.vec() {
+        [value::Thread(ref t)] => t.name.clone(),
+        [_] => panic!("Argument is not a thread"),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn thread_start(argv: super::Arguments) -> gc::Value {
+    let t = match argv.vec() {
+        [value::Thread(ref t)] => *t,
+        [_] => panic!("Argument is not a thread"),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    if t.state != State::New {
+        let irritants = irritant(argv.vm, &value::Thread(t));
+        argv.vm.error("thread", "thread already started".to_string(), irritants);
+    }
+
+    argv.vm.start_thread(t);
+    value::Thread(t)
+}
+
+pub fn thread_yield(argv: super::Arguments) -> gc::Value {
+    // yielding in a nested call is a no-op, see vm::thread
+    if argv.vm.nested == 0 {
+        argv.vm.switch_pending = true;
+    }
+
+    value::Unit
+}
+
+pub fn thread_join(argv: super::Arguments) -> gc::Value {
+    let mut t = match argv.vec() {
+        [value::Thread(ref t)] => *t,
+        [_] => panic!("Argument is not a thread"),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    if t.state == State::Terminated {
+        if t.failed {
+            argv.vm.raise_uncaught(t.result.clone());
+        }
+
+        return t.result.clone();
+    }
+
+    let cur = argv.vm.thread;
+    if t == cur {
+        let irritants = irritant(argv.vm, &value::Thread(t));
+        argv.vm.error("deadlock", "thread joins itself".to_string(), irritants);
+    }
+
+    argv.vm.block();
+    t.joiners.push(cur);
+    value::Unit
+}
+
+pub fn make_mutex(argv: super::Arguments) -> gc::Value {
+    let name = match argv.vec() {
+        [] => value::Unit,
+        [ref name] => name.clone(),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    value::Mutex(argv.vm.gc.alloc(thread::Mutex {
+        name: name,
+        owner: None,
+        waiters: ::std::collections::VecDeque::new()
+    }))
+}
+
+pub fn is_mutex(argv: super::Arguments) -> gc::Value {
+    match argv.vec() {
+        [value::Mutex(_)] => value::Bool(true),
+        [_] => value::Bool(false),
+        _ => panic!("Bad arguments")
+    }
+}
+
+pub fn mutex_lock(argv: super::Arguments) -> gc::Value {
+    let mut m = match argv.vec() {
+        [value::Mutex(ref m)] => *m,
+        [_] => panic!("Argument is not a mutex"),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    let cur = argv.vm.thread;
+
+    match m.owner {
+        // a mutex owned by a terminated thread is abandoned, it is locked
+        // before the error is raised, as in SRFI-18
+        Some(o) if o.state == State::Terminated => {
+            m.owner = Some(cur);
+            let irritants = irritant(argv.vm, &value::Mutex(m));
+            argv.vm.error("abandoned-mutex", "mutex abandoned by a terminated \
+                                             thread".to_string(), irritants)
+        }
+
+        Some(o) => {
+            if o == cur {
+                let irritants = irritant(argv.vm, &value::Mutex(m));
+                argv.vm.error("deadlock", "mutex already locked by the \
+                                           current thread".to_string(),
+                              irritants);
+            }
+
+            argv.vm.block();
+            m.waiters.push_back(cur);
+            value::Unit
+        }
+
+        None => {
+            m.owner = Some(cur);
+            value::Bool(true)
+        }
+    }
+}
+
+pub fn mutex_unlock(argv: super::Arguments) -> gc::Value {
+    let (mut m, cv) = match argv.vec() {
+        [value::Mutex(ref m)] => (*m, None),
+        [value::Mutex(ref m), value::CondVar(ref cv)] => (*m, Some(*cv)),
+        [_] | [_, _] => panic!("Bad arguments"),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    // ownership is passed to the first waiting thread
+    match m.waiters.pop_front() {
+        Some(w) => {
+            m.owner = Some(w);
+            argv.vm.wake(w, value::Bool(true));
+        }
+
+        None => m.owner = None
+    }
+
+    match cv {
+        Some(mut cv) => {
+            argv.vm.block();
+            let cur = argv.vm.thread;
+            cv.waiters.push_back(cur);
+        }
+
+        None => ()
+    }
+
+    value::Bool(true)
+}
+
+pub fn make_condition_variable(argv: super::Arguments) -> gc::Value {
+    let name = match argv.vec() {
+        [] => value::Unit,
+        [ref name] => name.clone(),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    value::CondVar(argv.vm.gc.alloc(thread::CondVar {
+        name: name,
+        waiters: ::std::collections::VecDeque::new()
+    }))
+}
+
+pub fn is_condition_variable(argv: super::Arguments) -> gc::Value {
+    match argv.vec() {
+        [value::CondVar(_)] => value::Bool(true),
+        [_] => value::Bool(false),
+        _ => panic!("Bad arguments")
+    }
+}
+
+pub fn condition_variable_signal(argv: super::Arguments) -> gc::Value {
+    let mut cv = match argv.vec() {
+        [value::CondVar(ref cv)] => *cv,
+        [_] => panic!("Argument is not a condition variable"),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    match cv.waiters.pop_front() {
+        Some(w) => argv.vm.wake(w, value::Bool(true)),
+        None => ()
+    }
+
+    value::Unit
+}
+
+pub fn condition_variable_broadcast(argv: super::Arguments) -> gc::Value {
+    let mut cv = match argv.vec() {
+        [value::CondVar(ref cv)] => *cv,
+        [_] => panic!("Argument is not a condition variable"),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    while let Some(w) = cv.waiters.pop_front() {
+        argv.vm.wake(w, value::Bool(true));
+    }
+
+    value::Unit
+}
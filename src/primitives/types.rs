@@ -1,5 +1,6 @@
 use gc;
 use gc::value;
+use primitives::num;
 
 pub fn boolean(argv: super::Arguments) -> gc::Value {
     match argv.vec() {
@@ -43,8 +44,23 @@ pub fn symbol(argv: super::Arguments) -> gc::Value {
 
 pub fn number(argv: super::Arguments) -> gc::Value {
     match argv.vec() {
-        [value::Num(_)] => value::Bool(true),
-        [_] => value::Bool(false),
+        [ref n] => value::Bool(num::is_number(n)),
+        _ => panic!("Bad arguments")
+    }
+}
+
+pub fn exact_integer(argv: super::Arguments) -> gc::Value {
+    match argv.vec() {
+        [ref n] => value::Bool(num::is_integer(n)),
+        _ => panic!("Bad arguments")
+    }
+}
+
+// flonums are rationals, except infinities and NaNs
+pub fn rational(argv: super::Arguments) -> gc::Value {
+    match argv.vec() {
+        [value::Flonum(x)] => value::Bool(x.is_finite()),
+        [ref n] => value::Bool(num::is_number(n)),
         _ => panic!("Bad arguments")
     }
 }
@@ -0,0 +1,227 @@
+use gc;
+use gc::value;
+use gc::value::list;
+use primitives::index;
+use primitives::irritant;
+use primitives::length;
+use primitives::range;
+use primitives::range_error;
+use primitives::type_error;
+use vm;
+
+// Vectors
+// fixed-size arrays of values, indexed in constant time. Vectors created
+// by programs are mutable, literals are not
+
+fn is_vector(v: &gc::Value) -> bool {
+    match v {
+        &value::Vector(_) => true,
+        _ => false
+    }
+}
+
+fn new_vector(vm: &mut vm::VM, values: Vec<gc::Value>) -> gc::Value {
+    value::Vector(vm.gc.alloc(gc::Vector { values: values, mutable: true }))
+}
+
+fn ptr(vm: &mut vm::VM, v: &gc::Value) -> gc::Ptr<gc::Vector> {
+    match v {
+        &value::Vector(vec) => vec,
+        v => type_error(vm, "a vector", v)
+    }
+}
+
+// a vector about to be modified
+fn mutable_ptr(vm: &mut vm::VM, v: &gc::Value) -> gc::Ptr<gc::Vector> {
+    let vec = ptr(vm, v);
+
+    if !vec.mutable {
+        let irritants = irritant(vm, v);
+        vm.error("vector", "vector is immutable".to_string(), irritants)
+    }
+
+    vec
+}
+
+pub fn is_vector_value(argv: super::Arguments) -> gc::Value {
+    match argv.vec() {
+        [ref v] => value::Bool(is_vector(v)),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+// (make-vector k [fill])
+pub fn make_vector(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    let (k, fill) = match &args[..] {
+        [ref k] => (length(vm, k), value::Bool(false)),
+        [ref k, ref fill] => (length(vm, k), fill.clone()),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    new_vector(vm, vec![fill; k])
+}
+
+pub fn vector(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    new_vector(argv.vm, args)
+}
+
+pub fn vector_length(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref v] => value::Fixnum(ptr(vm, v).values.len() as i64),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn vector_ref(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref v, ref k] => {
+            let vec = ptr(vm, v);
+            let k = index(vm, k);
+
+            match vec.values.get(k) {
+                Some(v) => v.clone(),
+                None => range_error(vm, "index", &args[1])
+            }
+        }
+
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn vector_set(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref v, ref k, ref obj] => {
+            let mut vec = mutable_ptr(vm, v);
+            let k = index(vm, k);
+
+            if k >= vec.values.len() {
+                range_error(vm, "index", &args[1])
+            }
+
+            vec.values[k] = obj.clone();
+        }
+
+        _ => panic!("Wrong number of arguments")
+    }
+
+    value::Unit
+}
+
+// (vector->list vector [start [end]])
+pub fn vector_to_list(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref v, ref bounds ..] if bounds.len() <= 2 => {
+            let vec = ptr(vm, v);
+            let (b, e) = range(vm, vec.values.len(), bounds);
+            let mut ret = value::Null;
+
+            for v in vec.values[b .. e].iter().rev() {
+                ret = value::Pair(list::cons(v, &ret, &mut *vm.gc));
+            }
+
+            ret
+        }
+
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn list_to_vector(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    let values = match &args[..] {
+        [ref l] => list::iter(l, |_| panic!("Argument is not a list")).collect(),
+        _ => panic!("Wrong number of arguments")
+    };
+
+    new_vector(vm, values)
+}
+
+// (vector-fill! vector fill [start [end]])
+pub fn vector_fill(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref v, ref fill, ref bounds ..] if bounds.len() <= 2 => {
+            let mut vec = mutable_ptr(vm, v);
+            let (b, e) = range(vm, vec.values.len(), bounds);
+
+            for v in vec.values[b .. e].iter_mut() {
+                *v = fill.clone();
+            }
+        }
+
+        _ => panic!("Wrong number of arguments")
+    }
+
+    value::Unit
+}
+
+// (vector-copy vector [start [end]])
+pub fn vector_copy(argv: super::Arguments) -> gc::Value {
+    let args = argv.vec().to_vec();
+    let vm = argv.vm;
+
+    match &args[..] {
+        [ref v, ref bounds ..] if bounds.len() <= 2 => {
+            let vec = ptr(vm, v);
+            let (b, e) = range(vm, vec.values.len(), bounds);
+            new_vector(vm, vec.values[b .. e].to_vec())
+        }
+
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+// the items of the vector argument of vector-map and vector-for-each,
+// copied since the function may modify the vector
+fn items(argv: &mut super::Arguments) -> (gc::Value, Vec<gc::Value>) {
+    let args = argv.vec().to_vec();
+
+    match &args[..] {
+        [ref fun, ref v] => (fun.clone(), ptr(argv.vm, v).values.clone()),
+        _ => panic!("Wrong number of arguments")
+    }
+}
+
+pub fn vector_map(mut argv: super::Arguments) -> gc::Value {
+    let (fun, items) = items(&mut argv);
+    let mut values = vec!();
+
+    for v in items.into_iter() {
+        // the argument of the function is passed on the stack
+        argv.vm.stack.push(v);
+        values.push(argv.vm.fun_call_ret(&fun, 1));
+    }
+
+    new_vector(argv.vm, values)
+}
+
+pub fn vector_for_each(mut argv: super::Arguments) -> gc::Value {
+    let (fun, items) = items(&mut argv);
+
+    for v in items.into_iter() {
+        argv.vm.stack.push(v);
+        argv.vm.fun_call_ret(&fun, 1);
+    }
+
+    value::Unit
+}
@@ -0,0 +1,70 @@
+use std::io;
+use std::io::BufRead;
+use std::io::Write;
+use std::process;
+
+use gc::value;
+use vm::VM;
+
+// A minimal interactive debugger, entered on interrupts
+// it reads commands on stdin until asked to resume the program
+
+static HELP: &'static str = "\
+commands:
+  bt, backtrace    print the frames of the current call chain
+  stack            print the values on the stack of the current frame
+  env              print the values of the current environment
+  raise            resume by raising an interrupt condition
+  c, continue      resume execution
+  q, quit          exit the program";
+
+impl VM {
+    pub fn debugger(&mut self) {
+        let stdin = io::stdin();
+        let mut err = io::stderr();
+
+        let _ = writeln!(err, "Interrupted at {}, type `help' for a list \
+                              of commands", self.describe_pc(self.frame.pc));
+
+        loop {
+            let _ = write!(err, "debug> ");
+            let _ = err.flush();
+
+            let mut line = String::new();
+            match stdin.lock().read_line(&mut line) {
+                Ok(0) | Err(_) => process::exit(130),
+                Ok(_) => ()
+            }
+
+            match line.trim() {
+                "" => (),
+                "bt" | "backtrace" => {
+                    let _ = write!(err, "{}", self.backtrace());
+                }
+
+                "stack" => {
+                    for v in self.stack[self.frame.sp ..].iter().rev() {
+                        let _ = writeln!(err, "  {}", v);
+                    }
+                }
+
+                "env" => {
+                    for (i, &(d, ref v)) in self.frame.env.values.iter().enumerate() {
+                        if d { let _ = writeln!(err, "  {:x}: {}", i, v); }
+                        else { let _ = writeln!(err, "  {:x}: <undefined>", i); }
+                    }
+                }
+
+                "raise" => {
+                    let msg = "interrupted by user".to_string();
+                    self.error("interrupt", msg, value::Null);
+                }
+
+                "c" | "continue" => return,
+                "q" | "quit" => process::exit(130),
+                "help" => { let _ = writeln!(err, "{}", HELP); }
+                cmd => { let _ = writeln!(err, "unknown command `{}'", cmd); }
+            }
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::fmt;
+
+use common::bytecode::base;
+use common::bytecode::off;
+use vm::Frame;
+use vm::VM;
+
+// Debug information
+// the optional debug section of a library relates offsets in its text
+// to the source it was compiled from. It gives the source position of
+// instructions, the name and parameters of the procedures, and the names
+// of the variables of each environment allocated by an Alloc
+
+pub struct Location {
+    pub line: u64,
+    pub column: u64
+}
+
+pub struct Procedure {
+    // offsets of the first instruction of the procedure, and past its last
+    pub entry: u32,
+    pub end: u32,
+    pub name: String,
+    pub params: Vec<String>
+}
+
+pub struct DebugInfo {
+    pub file: String,
+    // sorted by offset, a location holds until the next one
+    pub lines: Vec<(u32, Location)>,
+    pub procs: Vec<Procedure>,
+    // offsets of Alloc instructions, and the names of the variables
+    pub locals: Vec<(u32, Vec<String>)>
+}
+
+impl fmt::Display for Procedure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "({}", self.name));
+
+        for p in self.params.iter() {
+            try!(write!(f, " {}", p));
+        }
+
+        write!(f, ")")
+    }
+}
+
+impl DebugInfo {
+    pub fn location(&self, off: u32) -> Option<&Location> {
+        match self.lines.binary_search_by(|&(o, _)| o.cmp(&off)) {
+            Ok(i) => Some(&self.lines[i].1),
+            Err(0) => None,
+            Err(i) => Some(&self.lines[i - 1].1)
+        }
+    }
+
+    // the innermost procedure containing the given offset
+    pub fn procedure(&self, off: u32) -> Option<&Procedure> {
+        self.procs.iter()
+            .filter(|p| p.entry <= off && off < p.end)
+            .min_by_key(|p| p.end - p.entry)
+    }
+
+    pub fn procedure_at(&self, entry: u32) -> Option<&Procedure> {
+        self.procs.iter().find(|p| p.entry == entry)
+    }
+
+    pub fn locals(&self, alloc: u32) -> Option<&[String]> {
+        self.locals.iter().find(|&&(o, _)| o == alloc).map(|&(_, ref l)| &l[..])
+    }
+}
+
+impl VM {
+    fn debug_info(&self, pc: u64) -> Option<&DebugInfo> {
+        self.modules.get(base(pc) as usize).and_then(|lib| lib.debug.as_ref())
+    }
+
+    // the source position of a pc, such as file.scm:12:3, or
+    // (foo bar)+0x1f for libraries without debug information
+    pub fn location(&self, pc: u64) -> String {
+        match self.debug_info(pc) {
+            Some(d) => match d.location(off(pc)) {
+                Some(l) => format!("{}:{}:{}", d.file, l.line, l.column),
+                None => format!("{}+{:#x}", d.file, off(pc))
+            },
+
+            None => match self.modules.get(base(pc) as usize) {
+                Some(lib) => format!("{}+{:#x}", lib.name, off(pc)),
+                None => format!("{}:{:x}", base(pc), off(pc))
+            }
+        }
+    }
+
+    // a readable description of a pc, such as file.scm:12:3 in (foo x)
+    pub fn describe_pc(&self, pc: u64) -> String {
+        let loc = self.location(pc);
+
+        match self.debug_info(pc).and_then(|d| d.procedure(off(pc))) {
+            Some(p) => format!("{} in {}", loc, p),
+            None => loc
+        }
+    }
+
+    // the procedure running in a frame and where, such as
+    // (foo x) at file.scm:12:3
+    pub fn describe_frame(&self, f: &Frame) -> String {
+        let name = if off(f.entry) == 0 {
+            match self.modules.get(base(f.entry) as usize) {
+                Some(lib) => format!("toplevel of {}", lib.name),
+                None => format!("toplevel")
+            }
+        } else {
+            match self.debug_info(f.entry).and_then(|d| d.procedure_at(off(f.entry))) {
+                Some(p) => p.to_string(),
+                None => format!("procedure at {}", self.location(f.entry))
+            }
+        };
+
+        // the pc of a frame is past the start of the instruction
+        // it is executing
+        let pc = if off(f.pc) > 0 { f.pc - 1 } else { f.pc };
+        let mut s = format!("{} at {}", name, self.location(pc));
+
+        if f.tail_calls == 1 {
+            s.push_str(" (after a tail call)");
+        } else if f.tail_calls > 1 {
+            s.push_str(&format!(" (after {} tail calls)", f.tail_calls));
+        }
+
+        s
+    }
+}
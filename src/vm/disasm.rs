@@ -0,0 +1,228 @@
+use std::io;
+use std::io::Write;
+use std::path::Path;
+
+use common::bytecode;
+use common::bytecode::base;
+use common::bytecode::off;
+use gc::value;
+use primitives;
+use vm::VM;
+use vm::library::LibName;
+use vm::library::Library;
+use vm::library::LoadError;
+
+// A disassembler for the text of libraries, also used for tracing
+
+macro_rules! try_opt {
+    ($e:expr) => (match $e { Some(v) => v, None => return None })
+}
+
+fn read_be(prog: &[u8], off: usize, n: usize) -> Option<u64> {
+    if off + n > prog.len() {
+        return None
+    }
+
+    Some(prog[off .. off + n].iter().fold(0, |v, b| v << 8 | *b as u64))
+}
+
+// decode the instruction at the given offset, returning its text and
+// the offset of the next one, or None if it is not valid
+pub fn instr(lib: &Library, off: usize) -> Option<(String, usize)> {
+    let prog = &lib.prog[..];
+    let op = match prog.get(off) {
+        Some(op) => *op,
+        None => return None
+    };
+
+    let off = off + 1;
+
+    Some(match op {
+        x if x == bytecode::Nop as u8 => (format!("nop"), off),
+        x if x == bytecode::Pop as u8 => (format!("pop"), off),
+        x if x == bytecode::Return as u8 => (format!("return"), off),
+        x if x == bytecode::Call as u8 => {
+            (format!("call {}", try_opt!(read_be(prog, off, 1))), off + 1)
+        }
+
+        x if x == bytecode::Tcall as u8 => {
+            (format!("tcall {}", try_opt!(read_be(prog, off, 1))), off + 1)
+        }
+
+        x if x == bytecode::Jump as u8 => {
+            (format!("jump {:#06x}", try_opt!(read_be(prog, off, 4))), off + 4)
+        }
+
+        x if x == bytecode::Branch as u8 => {
+            (format!("branch {:#06x}", try_opt!(read_be(prog, off, 4))), off + 4)
+        }
+
+        x if x == bytecode::Fetch as u8 => {
+            (format!("fetch {}", try_opt!(read_be(prog, off, 8))), off + 8)
+        }
+
+        x if x == bytecode::Store as u8 => {
+            (format!("store {}", try_opt!(read_be(prog, off, 8))), off + 8)
+        }
+
+        x if x == bytecode::Alloc as u8 => {
+            let size = try_opt!(read_be(prog, off, 8));
+            let mut s = format!("alloc {}", size);
+
+            match lib.debug.as_ref().and_then(|d| d.locals(off as u32 - 1)) {
+                Some(names) => s.push_str(&format!(" ; {}", names.join(" "))),
+                None => ()
+            }
+
+            (s, off + 8)
+        }
+
+        x if x == bytecode::Push as u8 => {
+            let ty = try_opt!(read_be(prog, off, 1)) as u8;
+            let off = off + 1;
+
+            match ty {
+                t if t == bytecode::Unit as u8 => (format!("push unit"), off),
+                t if t == bytecode::Bool as u8 => {
+                    let b = try_opt!(read_be(prog, off, 1)) != 0;
+                    (format!("push bool {}", if b { "#t" } else { "#f" }), off + 1)
+                }
+
+                t if t == bytecode::Int as u8 => {
+                    let i = try_opt!(read_be(prog, off, 8)) as i64;
+                    (format!("push int {}", i), off + 8)
+                }
+
+                t if t == bytecode::Char as u8 => {
+                    let c = try_opt!(read_be(prog, off, 4)) as u32;
+                    let s = match ::std::char::from_u32(c) {
+                        Some(c) => format!("push char {:#x} ; {}", c as u32, value::Char(c)),
+                        None => format!("push char {:#x} ; ???", c)
+                    };
+
+                    (s, off + 4)
+                }
+
+                t if t == bytecode::Sym as u8 => {
+                    let i = try_opt!(read_be(prog, off, 8));
+                    let s = match lib.sym_table.get(i as usize) {
+                        Some(s) => format!("push sym {} ; {}", i, s.str),
+                        None => format!("push sym {} ; ???", i)
+                    };
+
+                    (s, off + 8)
+                }
+
+                t if t == bytecode::Prim as u8 => {
+                    let i = try_opt!(read_be(prog, off, 8));
+                    let s = match lib.prims.get(i as usize) {
+                        Some(&value::Primitive(_, name)) => match primitives::lookup(name) {
+                            Some(d) => format!("push prim {} ; {}/{}{}", i, name, d.arity,
+                                               if d.variadic { "+" } else { "" }),
+                            None => format!("push prim {} ; {}", i, name)
+                        },
+
+                        _ => format!("push prim {} ; ???", i)
+                    };
+
+                    (s, off + 8)
+                }
+
+                t if t == bytecode::Const as u8 => {
+                    let i = try_opt!(read_be(prog, off, 8));
+                    let s = match lib.consts.get(i as usize) {
+                        Some(c) => format!("push const {} ; {}", i, c),
+                        None => format!("push const {} ; ???", i)
+                    };
+
+                    (s, off + 8)
+                }
+
+                t if t == bytecode::Fun as u8 => {
+                    let entry = try_opt!(read_be(prog, off, 4)) as u32;
+                    let arity = try_opt!(read_be(prog, off + 4, 1));
+                    let variadic = try_opt!(read_be(prog, off + 5, 1)) != 0;
+                    let mut s = format!("push fun {:#06x} {}{}", entry, arity,
+                                        if variadic { "+" } else { "" });
+
+                    match lib.debug.as_ref().and_then(|d| d.procedure_at(entry)) {
+                        Some(p) => s.push_str(&format!(" ; {}", p)),
+                        None => ()
+                    }
+
+                    (s, off + 6)
+                }
+
+                _ => return None
+            }
+        }
+
+        _ => return None
+    })
+}
+
+impl VM {
+    // print the text of a library file on stdout
+    pub fn disassemble(&mut self, prog: &str) -> Result<(), LoadError> {
+        let name = Box::new(LibName(vec!(format!("main"))));
+        let lib = try!(Library::load_file(&mut *self.gc, Path::new(prog), name));
+
+        let out = io::stdout();
+        let mut out = out.lock();
+        let mut off = 0;
+        let mut line = None;
+
+        match lib.debug {
+            Some(ref d) => { let _ = writeln!(out, ";; {} from {}", prog, d.file); }
+            None => { let _ = writeln!(out, ";; {}", prog); }
+        }
+
+        while off < lib.prog.len() {
+            match lib.debug {
+                Some(ref d) => {
+                    match d.procedure_at(off as u32) {
+                        Some(p) => { let _ = writeln!(out, "\n{}:", p); }
+                        None => ()
+                    }
+
+                    match d.location(off as u32) {
+                        Some(l) if line != Some(l.line) => {
+                            line = Some(l.line);
+                            let _ = writeln!(out, "  ;; {}:{}", d.file, l.line);
+                        }
+
+                        _ => ()
+                    }
+                }
+
+                None => ()
+            }
+
+            match instr(&lib, off) {
+                Some((text, next)) => {
+                    let _ = writeln!(out, "  {:#06x}  {}", off, text);
+                    off = next;
+                }
+
+                None => {
+                    let _ = writeln!(out, "  {:#06x}  ??? {:#04x}", off, lib.prog[off]);
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // print the instruction about to be executed on stderr
+    pub fn trace_instr(&self) {
+        let pc = self.frame.pc;
+        let lib = &self.modules[base(pc) as usize];
+        let text = match instr(lib, off(pc) as usize) {
+            Some((text, _)) => text,
+            None => format!("???")
+        };
+
+        let _ = writeln!(io::stderr(), "[{}] {}", self.describe_pc(pc), text);
+    }
+}
@@ -0,0 +1,151 @@
+use std::fmt;
+use std::panic;
+
+use gc;
+use gc::value;
+use vm::VM;
+
+// Scheme-level errors
+// an error is raised by unwinding the native stack with a Throw payload
+// carrying the raised object, up to the innermost VM::catch (installed
+// by with-exception-handler or by the toplevel). Rust panics that do not
+// carry a Throw are bugs or unrecoverable errors and are not caught
+
+pub struct Throw(pub gc::Value);
+
+// panic payloads must be Send, although a Throw is always caught by the
+// thread that raised it, where its GC'd pointers are valid
+unsafe impl Send for Throw {}
+
+// number of frames kept at each end of a truncated backtrace
+static TRACE_KEPT: usize = 10;
+
+// a snapshot of the frame chain at the point an error was raised, with
+// the procedure and location of each frame. Procedures left by tail calls
+// have no frame, they are only counted. Deep traces (typically, stack
+// overflows) only keep their innermost and outermost frames
+
+pub struct Backtrace {
+    pub top: Vec<String>,
+    pub bottom: Vec<String>,
+    pub omitted: usize
+}
+
+impl Backtrace {
+    // one line per frame, innermost first
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec!();
+        let mut i = 0;
+
+        for f in self.top.iter() {
+            lines.push(format!("#{} {}", i, f));
+            i += 1;
+        }
+
+        if self.omitted > 0 {
+            lines.push(format!("... {} frames omitted", self.omitted));
+            i += self.omitted;
+        }
+
+        for f in self.bottom.iter() {
+            lines.push(format!("#{} {}", i, f));
+            i += 1;
+        }
+
+        lines
+    }
+}
+
+impl fmt::Display for Backtrace {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for l in self.lines().iter() {
+            try!(writeln!(fmt, "  {}", l));
+        }
+
+        Ok(())
+    }
+}
+
+impl VM {
+    pub fn backtrace(&self) -> Backtrace {
+        let mut frames = vec!();
+        let mut frame = Some(&*self.frame);
+
+        while let Some(f) = frame {
+            frames.push(f);
+            frame = f.caller.as_ref().map(|f| &**f);
+        }
+
+        let describe = |f: &&::vm::Frame| self.describe_frame(*f);
+
+        if frames.len() <= 2 * TRACE_KEPT {
+            let top = frames.iter().map(&describe).collect();
+            return Backtrace { top: top, bottom: vec!(), omitted: 0 }
+        }
+
+        let bottom = frames.split_off(frames.len() - TRACE_KEPT);
+        let omitted = frames.len() - TRACE_KEPT;
+        frames.truncate(TRACE_KEPT);
+
+        Backtrace {
+            top: frames.iter().map(&describe).collect(),
+            bottom: bottom.iter().map(&describe).collect(),
+            omitted: omitted
+        }
+    }
+
+    // raise an arbitrary Scheme object
+    pub fn raise(&mut self, obj: gc::Value) -> ! {
+        // resume_unwind doesn't invoke the panic hook, so nothing gets
+        // printed if the error is caught
+        panic::resume_unwind(Box::new(Throw(obj)))
+    }
+
+    // raise a condition object of the given kind, capturing a backtrace
+    pub fn error(&mut self, kind: &'static str, message: String,
+                 irritants: gc::Value) -> ! {
+        let trace = self.backtrace();
+        let cond = self.gc.alloc(gc::Condition {
+            kind: kind,
+            message: message,
+            irritants: irritants,
+            backtrace: trace
+        });
+
+        self.raise(value::Condition(cond))
+    }
+
+    // run f, returning Err with the raised object if it raises an error
+    // the frame chain and the stack are restored as they were when
+    // catch was called
+    pub fn catch<F, T>(&mut self, f: F) -> Result<T, gc::Value>
+        where F: FnOnce(&mut VM) -> T {
+        let depth = self.depth;
+        let sp = self.stack.len();
+        let nested = self.nested;
+
+        let res = {
+            let vm = &mut *self;
+            panic::catch_unwind(panic::AssertUnwindSafe(move || f(vm)))
+        };
+
+        match res {
+            Ok(v) => Ok(v),
+            Err(payload) => match payload.downcast::<Throw>() {
+                Ok(throw) => {
+                    while self.depth > depth {
+                        self.pop_frame();
+                    }
+
+                    self.stack.truncate(sp);
+                    self.nested = nested;
+
+                    let Throw(obj) = *throw;
+                    Err(obj)
+                }
+
+                Err(payload) => panic::resume_unwind(payload)
+            }
+        }
+    }
+}
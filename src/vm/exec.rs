@@ -1,5 +1,8 @@
 use std::collections::HashMap;
+use std::collections::VecDeque;
 use std::path::Path;
+use std::path::PathBuf;
+use std::sync::mpsc;
 
 use common::bytecode;
 use common::bytecode::base;
@@ -10,12 +13,25 @@ use gc;
 use gc::GC;
 use gc::Ptr;
 use gc::value;
-use gmp;
 use primitives;
 use vm::frame::Frame;
+use vm::interrupt;
+use vm::interrupt::OnInterrupt;
 use vm::library::Library;
 use vm::library::LibName;
+use vm::library::Import;
+use vm::library::LoadError;
+use vm::process::Message;
 use vm::Stack;
+use vm::thread;
+use vm::thread::Thread;
+
+// default limits on the execution state of a program. Exceeding them
+// raises a stack-overflow condition instead of exhausting the memory or
+// the native stack
+pub static DEFAULT_MAX_DEPTH: usize = 100000;
+pub static DEFAULT_MAX_STACK: usize = 1000000;
+pub static DEFAULT_MAX_NESTING: usize = 500;
 
 pub struct VM {
     pub frame: Box<Frame>,
@@ -23,7 +39,52 @@ pub struct VM {
     pub gc: Box<GC>,
 
     pub loaded_mods: HashMap<LibName, usize>,
-    pub modules: Vec<Box<Library>>
+    pub modules: Vec<Box<Library>>,
+    // libraries whose imports are being loaded, innermost last
+    loading: Vec<LibName>,
+
+    // directories given by the user to search libraries in, and the
+    // complete search path of the running program
+    pub lib_path: Vec<PathBuf>,
+    pub search_path: Vec<PathBuf>,
+
+    // length of the caller chain of the current frame
+    pub depth: usize,
+    // number of closure calls made from primitives currently running,
+    // each of them uses some native stack
+    pub nested: usize,
+
+    pub max_depth: usize,
+    pub max_stack: usize,
+    pub max_nesting: usize,
+
+    // what to do on Ctrl-C, once interrupt::install has been called
+    pub on_interrupt: OnInterrupt,
+    // false in spawned processes, which leave interrupts to the root one
+    pub root: bool,
+    // print each instruction before executing it
+    pub trace: bool,
+
+    // the running thread, and the one that runs the modules
+    pub thread: Ptr<Thread>,
+    pub primordial: Ptr<Thread>,
+    // every thread started and not yet terminated
+    pub threads: Vec<Ptr<Thread>>,
+    pub run_queue: VecDeque<Ptr<Thread>>,
+    // number of instructions after which the running thread is preempted,
+    // or 0 for cooperative scheduling
+    pub preempt: usize,
+    pub switch_pending: bool,
+    pub ticks: usize,
+
+    // messages sent to this process, and handles on the mailboxes of
+    // this process and of the one that spawned it
+    pub mailbox: mpsc::Receiver<Message>,
+    pub own_mailbox: mpsc::Sender<Message>,
+    pub parent_mailbox: Option<mpsc::Sender<Message>>,
+
+    // instructions executed since the last garbage collection
+    gc_ticks: u16
 }
 
 fn extend_sign(val: u64, nbytes: usize) -> i64 {
@@ -40,9 +101,19 @@ impl VM {
         let frame = Frame::new(env, 0, 0);
         let loaded_mods = HashMap::new();
         let mods = vec!();
+        let primordial = gc.alloc(Thread::new(value::Unit, value::Unit,
+                                              thread::State::Runnable));
+        let (tx, rx) = mpsc::channel();
 
         Box::new(VM { frame: frame, stack: stack, gc: gc, loaded_mods: loaded_mods,
-            modules: mods })
+            modules: mods, loading: vec!(), lib_path: vec!(), search_path: vec!(),
+            depth: 0, nested: 0, max_depth: DEFAULT_MAX_DEPTH,
+            max_stack: DEFAULT_MAX_STACK, max_nesting: DEFAULT_MAX_NESTING,
+            on_interrupt: OnInterrupt::Backtrace, root: true, trace: false,
+            thread: primordial, primordial: primordial, threads: vec!(primordial),
+            run_queue: VecDeque::new(), preempt: 0, switch_pending: false,
+            ticks: 0, mailbox: rx, own_mailbox: tx, parent_mailbox: None,
+            gc_ticks: 0 })
     }
 
     #[inline(always)]
@@ -58,6 +129,11 @@ impl VM {
     }
 
     fn push_frame(&mut self, pc: u64, env: Ptr<gc::Env>) {
+        if self.depth >= self.max_depth {
+            let msg = format!("maximum call depth ({}) exceeded", self.max_depth);
+            self.error("stack-overflow", msg, value::Null);
+        }
+
         let mut frame = Frame::new(env, self.stack.len(), pc);
         let mut nframe = Frame::new(env, 0, 0);
 
@@ -65,9 +141,10 @@ impl VM {
         frame.caller = Some(nframe);
 
         ::std::mem::swap(&mut self.frame, &mut frame);
+        self.depth += 1;
     }
 
-    fn pop_frame(&mut self) {
+    pub fn pop_frame(&mut self) {
         let mut nframe = Frame::new(Ptr(0 as *mut gc::ptr::Cell<gc::Env>), 0, 0);
 
         match self.frame.caller {
@@ -79,6 +156,15 @@ impl VM {
         }
 
         ::std::mem::swap(&mut self.frame, &mut nframe);
+        self.depth -= 1;
+    }
+
+    #[inline(always)]
+    fn check_stack(&mut self) {
+        if self.stack.len() > self.max_stack {
+            let msg = format!("maximum stack size ({}) exceeded", self.max_stack);
+            self.error("stack-overflow", msg, value::Null);
+        }
     }
 
     #[allow(dead_code)]
@@ -195,64 +281,98 @@ impl VM {
         self.read_le_int_n(2) as i16
     }
 
-    pub fn run(&mut self, prog: &str) {
+    pub fn run(&mut self, prog: &str) -> Result<(), LoadError> {
         let p = Path::new(prog);
+        self.search_path = Library::library_path(&self.lib_path, &p);
+
         let name = vec!(format!("main"));
-        let l = Library::load_file(&mut *self.gc, &p, Box::new(LibName(name)));
-        self.load_module(l);
+        let l = try!(Library::load_file(&mut *self.gc, &p, Box::new(LibName(name))));
+        self.load_module(l)
     }
 
-    fn load_module(&mut self, lib: Box<Library>) {
-        let mut env = Some(primitives::env(&mut *self.gc));
+    fn load_module(&mut self, lib: Box<Library>) -> Result<(), LoadError> {
+        // register the library first, so that its environment, where the
+        // imported values are chained, is seen by the GC while the
+        // imported libraries are executed
+        let idx = self.modules.len();
+        let imports = lib.imports.clone();
+        let mut lenv = lib.env;
+        lenv.next = Some(primitives::env(&mut *self.gc));
+        self.modules.push(lib);
+
+        let name = self.modules[idx].name.clone();
+        self.loading.push(*name.clone());
+        let res = self.import_all(&imports, &mut lenv);
+        self.loading.pop();
+        try!(res);
+
+        self.loaded_mods.insert(*name, idx);
+        let base = (idx as u64) << 32;
+
+        // load initial frame
+        self.stack = vec!();
+        self.frame.env = lenv;
+        self.frame.pc = base;
+        self.frame.entry = base;
+        self.frame.tail_calls = 0;
+        self.frame.sp = 0;
+        self.frame.caller = None;
+        self.depth = 0;
+
+        // exec module
+        self.exec_module(idx);
+        Ok(())
+    }
 
-        for i in lib.imports.iter() {
-            debug!("Require lib");
-            let l = match self.loaded_mods.get(&**i).map(|&x| x) {
+    // chain the environments of the imported libraries onto the
+    // environment of an importing library, loading them if needed
+    fn import_all(&mut self, imports: &[Import],
+                  lenv: &mut Ptr<gc::Env>) -> Result<(), LoadError> {
+        for i in imports.iter() {
+            debug!("Require lib {}", i.lib);
+            let l = match self.loaded_mods.get(&*i.lib).map(|&x| x) {
                 None => {
-                    let l = Library::load(&mut *self.gc, &**i,
-                                          Library::library_path(None));
-                    self.load_module(l);
-                    &**self.modules.last().unwrap()
+                    // a library that is still loading its own imports
+                    // can't be imported, since its body hasn't run yet
+                    match self.loading.iter().position(|n| *n == *i.lib) {
+                        Some(pos) => {
+                            let mut cycle = self.loading[pos ..].to_vec();
+                            cycle.push(*i.lib.clone());
+                            return Err(LoadError::Cycle(cycle))
+                        }
+
+                        None => ()
+                    }
+
+                    let l = try!(Library::load(&mut *self.gc, &*i.lib,
+                                               &self.search_path));
+                    try!(self.load_module(l));
+                    self.loaded_mods[&*i.lib]
                 }
 
-                Some(idx) => &*self.modules[idx]
+                Some(idx) => idx
             };
 
+            let l = &*self.modules[l];
+            let slots = try!(i.resolve(&mut *self.gc, l));
             let mut nenv = self.gc.alloc(gc::Env {
-                values: Vec::with_capacity(l.exports as usize),
-                next: env
+                values: Vec::with_capacity(slots.len()),
+                next: lenv.next
             });
 
-            let mut i = 0;
-
-            // FIXME: why is this neccessary?
-            let e = l.env;
+            for slot in slots.iter() {
+                let item = match l.env.values.get(*slot as usize) {
+                    Some(item) => item.clone(),
+                    None => return Err(LoadError::MissingSlot(*l.name.clone(), *slot))
+                };
 
-            for &(_, ref e) in e.values.iter() {
-                nenv.store(e, i);
-                i += 1;
+                nenv.values.push(item);
             }
 
-            env = Some(nenv);
+            lenv.next = Some(nenv);
         }
 
-        let mut nenv = lib.env;
-        nenv.next = env;
-
-        let idx = self.modules.len();
-        self.loaded_mods.insert(*lib.name.clone(), idx);
-        self.modules.push(lib);
-        let base = (idx as u64) << 32;
-
-        // load initial frame
-        self.stack = vec!();
-        self.frame.env = nenv;
-        self.frame.pc = base;
-        self.frame.sp = 0;
-        self.frame.caller = None;
-
-        // exec module
-        self.exec_module();
+        Ok(())
     }
 
     // Returns an environment containings the arguments of a closure,
@@ -329,6 +449,8 @@ impl VM {
 
     #[inline(always)]
     pub fn fun_call(&mut self, fun: &value::Value, argc: u8) {
+        self.check_stack();
+
         match fun {
             &value::Closure(cl) => self.closure_call(cl, argc),
             &value::Primitive(prim, _) => {
@@ -341,6 +463,8 @@ impl VM {
 
     #[inline(always)]
     pub fn fun_call_ret(&mut self, fun: &value::Value, argc: u8) -> value::Value {
+        self.check_stack();
+
         match fun {
             &value::Primitive(prim, _) => self.prim_call(prim, argc),
 
@@ -350,7 +474,14 @@ impl VM {
                     let f: &Frame = &*v.frame; f as *const Frame
                 }
 
+                if self.nested >= self.max_nesting {
+                    let msg = format!("maximum nesting of calls from primitives \
+                                       ({}) exceeded", self.max_nesting);
+                    self.error("stack-overflow", msg, value::Null);
+                }
+
                 let caller: *const Frame = get_cur_frame(self);
+                self.nested += 1;
                 self.closure_call(cl, argc);
                 let mut cur_frame = get_cur_frame(self);
 
@@ -360,8 +491,16 @@ impl VM {
                 while cur_frame != caller {
                     self.exec_instr();
                     cur_frame = get_cur_frame(self);
+
+                    // no garbage collection here, since the primitive
+                    // may hold values the GC can't see, but this is
+                    // still a safe point for interrupts
+                    if self.root && interrupt::pending() {
+                        self.interrupt();
+                    }
                 }
 
+                self.nested -= 1;
                 self.stack.pop().unwrap()
             }
 
@@ -370,6 +509,10 @@ impl VM {
     }
 
     fn exec_instr(&mut self) {
+        if self.trace {
+            self.trace_instr();
+        }
+
         let opcode = self.next_op();
         debug!("Executing next instruction: {:?}", opcode);
 
@@ -409,7 +552,13 @@ impl VM {
 
                     bytecode::Int => {
                         let i = self.read_be_i64();
-                        value::Num(gmp::mpz::Mpz::from(i))
+                        value::Fixnum(i)
+                    }
+
+                    bytecode::Char => {
+                        // code points are checked when libraries are loaded
+                        let c = self.read_be_u32();
+                        value::Char(::std::char::from_u32(c).unwrap())
                     }
 
                     bytecode::Sym => {
@@ -425,7 +574,7 @@ impl VM {
                         let arg = self.read_be_u32();
                         let arity = self.read_u8();
                         let variadic = self.read_u8() != 0x00;
-                        let base = self.frame.pc & 0xFFFF0000;
+                        let base = self.frame.pc & 0xFFFFFFFF00000000;
                         let clpc = (arg as u64) | base;
                         let env = self.frame.env;
 
@@ -440,7 +589,15 @@ impl VM {
                     }
 
                     bytecode::Prim => {
-                        panic!("Unimplemented");
+                        let base = base(self.frame.pc);
+                        let arg = self.read_be_u64();
+                        self.modules[base as usize].prims[arg as usize].clone()
+                    }
+
+                    bytecode::Const => {
+                        let base = base(self.frame.pc);
+                        let arg = self.read_be_u64();
+                        self.modules[base as usize].consts[arg as usize].clone()
                     }
                 };
 
@@ -474,6 +631,8 @@ impl VM {
                         self.frame.sp = self.stack.len();
                         self.frame.pc = cl.pc;
                         self.frame.env = env;
+                        self.frame.entry = cl.pc;
+                        self.frame.tail_calls += 1;
                     }
 
                     // the compiler doesn't make the difference between
@@ -490,7 +649,7 @@ impl VM {
 
             bytecode::Jump => {
                 let dst = self.read_be_u32();
-                self.frame.pc = self.frame.pc & 0x00000000;
+                self.frame.pc = self.frame.pc & 0xFFFFFFFF00000000;
                 self.frame.pc = self.frame.pc | (dst as u64);
             }
 
@@ -500,7 +659,7 @@ impl VM {
 
                 match expr {
                     value::Bool(false) => {
-                        self.frame.pc = self.frame.pc & 0x00000000;
+                        self.frame.pc = self.frame.pc & 0xFFFFFFFF00000000;
                         self.frame.pc = self.frame.pc | (dst as u64);
                     }
 
@@ -530,24 +689,76 @@ impl VM {
         }
     }
 
-    fn exec_module(&mut self) {
-        use gc::visit::Visitor;
-
+    fn exec_module(&mut self, idx: usize) {
         debug!("Begin module execution");
-        let prog_len = self.modules.last().unwrap().prog.len();
+        let prog_len = self.modules[idx].prog.len();
         debug!("Module section is {} long", prog_len);
-        let mut counter = 0u16;
+        let end = ((idx as u64) << 32) | prog_len as u64;
+
+        loop {
+            match self.catch(|vm| vm.exec_slice(end)) {
+                Ok(true) => break,
+                Ok(false) => self.schedule(),
+
+                // an uncaught error terminates the thread that raised it,
+                // or the program if it is the primordial thread
+                Err(obj) => {
+                    if self.thread == self.primordial {
+                        self.raise(obj);
+                    }
+
+                    self.thread_exit(obj, true);
+                    self.schedule();
+                }
+            }
+        }
+    }
+
+    // run the current thread until it has to be switched, in which case
+    // return false, or until the module's toplevel reaches end
+    fn exec_slice(&mut self, end: u64) -> bool {
+        use gc::visit::Visitor;
+
+        // the thread was woken up by the termination of a thread it joined
+        match self.thread.pending.take() {
+            Some(obj) => self.raise_uncaught(obj),
+            None => ()
+        }
+
+        loop {
+            if self.thread != self.primordial {
+                // the thread's thunk returned to the empty initial frame
+                if self.depth == 0 {
+                    let ret = self.stack.pop().unwrap();
+                    self.thread_exit(ret, false);
+                    return false;
+                }
+            } else if self.depth == 0 && self.frame.pc >= end {
+                return true;
+            }
 
-        while (off(self.frame.pc) as usize) < prog_len {
             self.exec_instr();
-            counter += 1;
+            self.gc_ticks += 1;
+            self.ticks += 1;
 
-            if counter == 2000 {
+            if self.gc_ticks == 2000 {
                 // garbage-collect
                 let visitors = &mut [&mut self.stack as &mut Visitor,
-                    &mut *self.frame as &mut Visitor];
+                    &mut *self.frame as &mut Visitor,
+                    &mut self.threads as &mut Visitor,
+                    &mut self.modules as &mut Visitor];
                 self.gc.sweep(visitors);
-                counter = 0;
+                self.gc_ticks = 0;
+            }
+
+            if self.root && interrupt::pending() {
+                self.interrupt();
+            }
+
+            if self.switch_pending ||
+               (self.preempt > 0 && self.ticks >= self.preempt &&
+                !self.run_queue.is_empty()) {
+                return false;
             }
         }
     }
@@ -4,12 +4,20 @@ pub struct Frame {
     pub env: gc::Ptr<gc::Env>,
     pub sp: usize,
     pub pc: u64,
-    pub caller: Option<Box<Frame>>
+    pub caller: Option<Box<Frame>>,
+
+    // entry point of the procedure running in this frame, and the number
+    // of tail calls which reused the frame, for backtraces
+    pub entry: u64,
+    pub tail_calls: usize
 }
 
 impl Frame {
     pub fn new(base_env: gc::Ptr<gc::Env>, sp: usize, pc: u64) -> Box<Frame> {
-        Box::new(Frame { env: base_env, sp: sp, pc: pc, caller: None })
+        Box::new(Frame {
+            env: base_env, sp: sp, pc: pc, caller: None,
+            entry: pc, tail_calls: 0
+        })
     }
 
     pub fn alloc(&mut self, gc: &mut gc::GC, size: u64) {
@@ -30,3 +38,15 @@ impl Frame {
         self.env.fetch(addr)
     }
 }
+
+impl Drop for Frame {
+    fn drop(&mut self) {
+        // unlink the caller chain iteratively, dropping a deep
+        // chain recursively would overflow the native stack
+        let mut caller = self.caller.take();
+
+        while let Some(mut f) = caller {
+            caller = f.caller.take();
+        }
+    }
+}
@@ -0,0 +1,987 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+
+use gc;
+use gc::value;
+use gmp;
+use primitives;
+use vm::VM;
+use vm::debuginfo::DebugInfo;
+use vm::debuginfo::Location;
+use vm::debuginfo::Procedure;
+use vm::library::LibName;
+use vm::library::Library;
+
+// Heap images
+// an image is a snapshot of a VM after its libraries have been loaded and
+// executed: the modules, the interned strings and the heap reachable from
+// them. Starting from an image skips loading and initialising those
+// libraries. The main program of the dumping VM is kept in the image,
+// since its closures may be referenced, but it is not registered as a
+// loaded library.
+// Threads, mutexes, mailboxes and conditions can't be dumped.
+
+static MAGIC: &'static [u8] = b"SCI";
+static VERSION: u8 = 0x0D;
+
+// objects
+static OBJ_STRING: u8 = 0x00;
+static OBJ_PAIR: u8 = 0x01;
+static OBJ_ENV: u8 = 0x02;
+static OBJ_CLOSURE: u8 = 0x03;
+static OBJ_VECTOR: u8 = 0x04;
+static OBJ_BYTEVECTOR: u8 = 0x05;
+static OBJ_HASHTABLE: u8 = 0x06;
+static OBJ_END: u8 = 0xFF;
+
+// values
+static VAL_UNIT: u8 = 0x00;
+static VAL_BOOL: u8 = 0x01;
+static VAL_NUM: u8 = 0x02;
+static VAL_NULL: u8 = 0x03;
+static VAL_PAIR: u8 = 0x04;
+static VAL_CLOSURE: u8 = 0x05;
+static VAL_STRING: u8 = 0x06;
+static VAL_SYMBOL: u8 = 0x07;
+static VAL_PRIMITIVE: u8 = 0x08;
+static VAL_FIXNUM: u8 = 0x09;
+static VAL_RATIO: u8 = 0x0A;
+static VAL_FLONUM: u8 = 0x0B;
+static VAL_CHAR: u8 = 0x0C;
+static VAL_VECTOR: u8 = 0x0D;
+static VAL_BYTEVECTOR: u8 = 0x0E;
+static VAL_HASHTABLE: u8 = 0x0F;
+
+pub enum ImageError {
+    Io(io::Error),
+    // a value of the heap that can't be stored in an image
+    Unsupported(String),
+    Format(&'static str)
+}
+
+impl From<io::Error> for ImageError {
+    fn from(e: io::Error) -> ImageError {
+        ImageError::Io(e)
+    }
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &ImageError::Io(ref e) => write!(f, "cannot access image: {}", e),
+            &ImageError::Unsupported(ref v) => {
+                write!(f, "cannot store {} in an image", v)
+            }
+
+            &ImageError::Format(msg) => write!(f, "invalid image: {}", msg)
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Obj {
+    String(gc::Ptr<gc::String>),
+    Pair(gc::Ptr<gc::Pair>),
+    Env(gc::Ptr<gc::Env>),
+    Closure(gc::Ptr<gc::Closure>),
+    Vector(gc::Ptr<gc::Vector>),
+    Bytevector(gc::Ptr<gc::Bytevector>),
+    HashTable(gc::Ptr<gc::HashTable>)
+}
+
+impl Obj {
+    fn addr(&self) -> usize {
+        match self {
+            &Obj::String(gc::Ptr(p)) => p as usize,
+            &Obj::Pair(gc::Ptr(p)) => p as usize,
+            &Obj::Env(gc::Ptr(p)) => p as usize,
+            &Obj::Closure(gc::Ptr(p)) => p as usize,
+            &Obj::Vector(gc::Ptr(p)) => p as usize,
+            &Obj::Bytevector(gc::Ptr(p)) => p as usize,
+            &Obj::HashTable(gc::Ptr(p)) => p as usize
+        }
+    }
+}
+
+fn write_u8<W: Write>(out: &mut W, b: u8) -> io::Result<()> {
+    out.write_all(&[b])
+}
+
+fn write_be_u64<W: Write>(out: &mut W, n: u64) -> io::Result<()> {
+    let mut buf = [0u8; 8];
+
+    for i in 0 .. 8 {
+        buf[i] = (n >> (56 - 8 * i)) as u8;
+    }
+
+    out.write_all(&buf)
+}
+
+fn write_str<W: Write>(out: &mut W, s: &str) -> io::Result<()> {
+    try!(write_be_u64(out, s.len() as u64));
+    out.write_all(s.as_bytes())
+}
+
+fn read_u8<R: Read>(inp: &mut R) -> io::Result<u8> {
+    let mut buf = [0u8];
+    try!(inp.read_exact(&mut buf));
+    Ok(buf[0])
+}
+
+fn read_be_u64<R: Read>(inp: &mut R) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    try!(inp.read_exact(&mut buf));
+    Ok(buf.iter().fold(0, |n, b| n << 8 | *b as u64))
+}
+
+fn read_str<R: Read>(inp: &mut R) -> Result<String, ImageError> {
+    let len = try!(read_be_u64(inp));
+    let mut buf = vec!();
+    try!(inp.take(len).read_to_end(&mut buf));
+
+    if buf.len() as u64 != len {
+        return Err(ImageError::Format("truncated string"))
+    }
+
+    String::from_utf8(buf).map_err(|_| ImageError::Format("invalid string"))
+}
+
+// writes objects as they are found, so that no recursion is needed
+// for deep structures
+
+struct Dumper<W: Write> {
+    out: W,
+    ids: HashMap<usize, u64>,
+    queue: VecDeque<Obj>
+}
+
+impl<W: Write> Dumper<W> {
+    fn id(&mut self, obj: Obj) -> u64 {
+        let next = self.ids.len() as u64;
+
+        match self.ids.get(&obj.addr()) {
+            Some(id) => return *id,
+            None => ()
+        }
+
+        self.ids.insert(obj.addr(), next);
+        self.queue.push_back(obj);
+        next
+    }
+
+    fn write_id(&mut self, obj: Obj) -> io::Result<()> {
+        let id = self.id(obj);
+        write_be_u64(&mut self.out, id)
+    }
+
+    fn write_value(&mut self, v: &gc::Value) -> Result<(), ImageError> {
+        match v {
+            &value::Unit => try!(write_u8(&mut self.out, VAL_UNIT)),
+            &value::Null => try!(write_u8(&mut self.out, VAL_NULL)),
+            &value::Bool(b) => {
+                try!(write_u8(&mut self.out, VAL_BOOL));
+                try!(write_u8(&mut self.out, b as u8));
+            }
+
+            &value::Char(c) => {
+                try!(write_u8(&mut self.out, VAL_CHAR));
+                try!(write_be_u64(&mut self.out, c as u64));
+            }
+
+            &value::Fixnum(i) => {
+                try!(write_u8(&mut self.out, VAL_FIXNUM));
+                try!(write_be_u64(&mut self.out, i as u64));
+            }
+
+            &value::Flonum(f) => {
+                try!(write_u8(&mut self.out, VAL_FLONUM));
+                try!(write_be_u64(&mut self.out, f.to_bits()));
+            }
+
+            &value::Num(ref n) => {
+                try!(write_u8(&mut self.out, VAL_NUM));
+                try!(write_str(&mut self.out, &n.to_str_radix(16)));
+            }
+
+            &value::Ratio(ref q) => {
+                try!(write_u8(&mut self.out, VAL_RATIO));
+                try!(write_str(&mut self.out, &q.get_num().to_str_radix(16)));
+                try!(write_str(&mut self.out, &q.get_den().to_str_radix(16)));
+            }
+
+            &value::Pair(p) => {
+                try!(write_u8(&mut self.out, VAL_PAIR));
+                try!(self.write_id(Obj::Pair(p)));
+            }
+
+            &value::Closure(c) => {
+                try!(write_u8(&mut self.out, VAL_CLOSURE));
+                try!(self.write_id(Obj::Closure(c)));
+            }
+
+            &value::String(s) => {
+                try!(write_u8(&mut self.out, VAL_STRING));
+                try!(self.write_id(Obj::String(s)));
+            }
+
+            &value::Symbol(s) => {
+                try!(write_u8(&mut self.out, VAL_SYMBOL));
+                try!(self.write_id(Obj::String(s)));
+            }
+
+            &value::Vector(vec) => {
+                try!(write_u8(&mut self.out, VAL_VECTOR));
+                try!(self.write_id(Obj::Vector(vec)));
+            }
+
+            &value::Bytevector(bv) => {
+                try!(write_u8(&mut self.out, VAL_BYTEVECTOR));
+                try!(self.write_id(Obj::Bytevector(bv)));
+            }
+
+            &value::HashTable(t) => {
+                try!(write_u8(&mut self.out, VAL_HASHTABLE));
+                try!(self.write_id(Obj::HashTable(t)));
+            }
+
+            &value::Primitive(_, name) => {
+                try!(write_u8(&mut self.out, VAL_PRIMITIVE));
+                try!(write_str(&mut self.out, name));
+            }
+
+            _ => return Err(ImageError::Unsupported(format!("{}", v)))
+        }
+
+        Ok(())
+    }
+
+    fn write_obj(&mut self, obj: Obj) -> Result<(), ImageError> {
+        match obj {
+            Obj::String(s) => {
+                try!(write_u8(&mut self.out, OBJ_STRING));
+                try!(write_u8(&mut self.out, s.mutable as u8));
+                try!(write_str(&mut self.out, &s.str));
+            }
+
+            Obj::Pair(p) => {
+                try!(write_u8(&mut self.out, OBJ_PAIR));
+                try!(self.write_value(&p.car));
+                try!(self.write_value(&p.cdr));
+            }
+
+            Obj::Env(e) => {
+                // the capacity of an environment is its size, see gc::Env.
+                // Every slot is written, the ones past its length as
+                // undefined, so that its size is bounded by the image
+                try!(write_u8(&mut self.out, OBJ_ENV));
+                try!(write_be_u64(&mut self.out, e.values.capacity() as u64));
+
+                for i in 0 .. e.values.capacity() {
+                    match e.values.get(i) {
+                        Some(&(true, ref v)) => {
+                            try!(write_u8(&mut self.out, 1));
+                            try!(self.write_value(v));
+                        }
+
+                        _ => {
+                            try!(write_u8(&mut self.out, 0));
+                            try!(self.write_value(&value::Unit));
+                        }
+                    }
+                }
+
+                match e.next {
+                    Some(next) => {
+                        try!(write_u8(&mut self.out, 1));
+                        try!(self.write_id(Obj::Env(next)));
+                    }
+
+                    None => try!(write_u8(&mut self.out, 0))
+                }
+            }
+
+            Obj::Closure(c) => {
+                try!(write_u8(&mut self.out, OBJ_CLOSURE));
+                try!(write_be_u64(&mut self.out, c.pc));
+                try!(self.write_id(Obj::Env(c.env)));
+                try!(write_u8(&mut self.out, c.arity));
+                try!(write_u8(&mut self.out, c.variadic as u8));
+            }
+
+            Obj::Vector(vec) => {
+                try!(write_u8(&mut self.out, OBJ_VECTOR));
+                try!(write_u8(&mut self.out, vec.mutable as u8));
+                try!(write_be_u64(&mut self.out, vec.values.len() as u64));
+
+                for v in vec.values.iter() {
+                    try!(self.write_value(v));
+                }
+            }
+
+            Obj::Bytevector(bv) => {
+                try!(write_u8(&mut self.out, OBJ_BYTEVECTOR));
+                try!(write_u8(&mut self.out, bv.mutable as u8));
+                try!(write_be_u64(&mut self.out, bv.bytes.len() as u64));
+                try!(self.out.write_all(&bv.bytes));
+            }
+
+            Obj::HashTable(t) => {
+                let entries = t.entries();
+                try!(write_u8(&mut self.out, OBJ_HASHTABLE));
+                try!(write_u8(&mut self.out, t.equiv as u8));
+                try!(write_be_u64(&mut self.out, entries.len() as u64));
+
+                for &(ref k, ref v) in entries.iter() {
+                    try!(self.write_value(k));
+                    try!(self.write_value(v));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_ids(&mut self, strs: &[gc::Ptr<gc::String>]) -> io::Result<()> {
+        try!(write_be_u64(&mut self.out, strs.len() as u64));
+
+        for s in strs.iter() {
+            try!(self.write_id(Obj::String(*s)));
+        }
+
+        Ok(())
+    }
+}
+
+fn write_libname<W: Write>(out: &mut W, name: &LibName) -> io::Result<()> {
+    let &LibName(ref parts) = name;
+    try!(write_be_u64(out, parts.len() as u64));
+
+    for p in parts.iter() {
+        try!(write_str(out, p));
+    }
+
+    Ok(())
+}
+
+fn write_strs<W: Write>(out: &mut W, strs: &[String]) -> io::Result<()> {
+    try!(write_be_u64(out, strs.len() as u64));
+
+    for s in strs.iter() {
+        try!(write_str(out, s));
+    }
+
+    Ok(())
+}
+
+fn read_strs<R: Read>(inp: &mut R) -> Result<Vec<String>, ImageError> {
+    let count = try!(read_be_u64(inp));
+    let mut strs = vec!();
+
+    for _ in 0 .. count {
+        strs.push(try!(read_str(inp)));
+    }
+
+    Ok(strs)
+}
+
+fn write_debug<W: Write>(out: &mut W, d: &Option<DebugInfo>) -> io::Result<()> {
+    let d = match d {
+        &Some(ref d) => d,
+        &None => return write_u8(out, 0)
+    };
+
+    try!(write_u8(out, 1));
+    try!(write_str(out, &d.file));
+
+    try!(write_be_u64(out, d.lines.len() as u64));
+    for &(off, ref l) in d.lines.iter() {
+        try!(write_be_u64(out, off as u64));
+        try!(write_be_u64(out, l.line));
+        try!(write_be_u64(out, l.column));
+    }
+
+    try!(write_be_u64(out, d.procs.len() as u64));
+    for p in d.procs.iter() {
+        try!(write_be_u64(out, p.entry as u64));
+        try!(write_be_u64(out, p.end as u64));
+        try!(write_str(out, &p.name));
+        try!(write_strs(out, &p.params));
+    }
+
+    try!(write_be_u64(out, d.locals.len() as u64));
+    for &(off, ref names) in d.locals.iter() {
+        try!(write_be_u64(out, off as u64));
+        try!(write_strs(out, names));
+    }
+
+    Ok(())
+}
+
+fn read_debug<R: Read>(inp: &mut R) -> Result<Option<DebugInfo>, ImageError> {
+    if try!(read_u8(inp)) == 0 {
+        return Ok(None)
+    }
+
+    let file = try!(read_str(inp));
+
+    let count = try!(read_be_u64(inp));
+    let mut lines = vec!();
+    for _ in 0 .. count {
+        let off = try!(read_be_u64(inp)) as u32;
+        let line = try!(read_be_u64(inp));
+        let column = try!(read_be_u64(inp));
+        lines.push((off, Location { line: line, column: column }));
+    }
+
+    let count = try!(read_be_u64(inp));
+    let mut procs = vec!();
+    for _ in 0 .. count {
+        let entry = try!(read_be_u64(inp)) as u32;
+        let end = try!(read_be_u64(inp)) as u32;
+        let name = try!(read_str(inp));
+        let params = try!(read_strs(inp));
+        procs.push(Procedure { entry: entry, end: end, name: name, params: params });
+    }
+
+    let count = try!(read_be_u64(inp));
+    let mut locals = vec!();
+    for _ in 0 .. count {
+        let off = try!(read_be_u64(inp)) as u32;
+        locals.push((off, try!(read_strs(inp))));
+    }
+
+    Ok(Some(DebugInfo { file: file, lines: lines, procs: procs, locals: locals }))
+}
+
+fn read_libname<R: Read>(inp: &mut R) -> Result<LibName, ImageError> {
+    let count = try!(read_be_u64(inp));
+    let mut parts = vec!();
+
+    for _ in 0 .. count {
+        parts.push(try!(read_str(inp)));
+    }
+
+    Ok(LibName(parts))
+}
+
+// objects and values, as read from the image, before their
+// references are resolved
+
+enum RawValue {
+    Unit,
+    Bool(bool),
+    Char(char),
+    Fixnum(i64),
+    Flonum(f64),
+    Num(gmp::mpz::Mpz),
+    Ratio(gmp::mpq::Mpq),
+    Null,
+    Ref(u8, u64),
+    Primitive(String)
+}
+
+enum RawObj {
+    String(bool, String),
+    Pair(RawValue, RawValue),
+    Env(Vec<(bool, RawValue)>, Option<u64>),
+    Closure(u64, u64, u8, bool),
+    Vector(bool, Vec<RawValue>),
+    Bytevector(bool, Vec<u8>),
+    HashTable(gc::Equiv, Vec<(RawValue, RawValue)>)
+}
+
+struct RawLibrary {
+    name: LibName,
+    prog: Vec<u8>,
+    env: u64,
+    sym_table: Vec<u64>,
+    exports: u64,
+    export_names: Vec<u64>,
+    debug: Option<DebugInfo>,
+    consts: Vec<RawValue>,
+    prims: Vec<RawValue>
+}
+
+fn read_raw_value<R: Read>(inp: &mut R) -> Result<RawValue, ImageError> {
+    let tag = try!(read_u8(inp));
+
+    Ok(match tag {
+        t if t == VAL_UNIT => RawValue::Unit,
+        t if t == VAL_NULL => RawValue::Null,
+        t if t == VAL_BOOL => RawValue::Bool(try!(read_u8(inp)) != 0),
+        t if t == VAL_NUM => {
+            let s = try!(read_str(inp));
+            match gmp::mpz::Mpz::from_str_radix(&s, 16) {
+                Ok(n) => RawValue::Num(n),
+                Err(_) => return Err(ImageError::Format("invalid number"))
+            }
+        }
+
+        t if t == VAL_RATIO => {
+            let n = try!(read_str(inp));
+            let d = try!(read_str(inp));
+            match (gmp::mpz::Mpz::from_str_radix(&n, 16),
+                   gmp::mpz::Mpz::from_str_radix(&d, 16)) {
+                (Ok(ref n), Ok(ref d)) if *d != gmp::mpz::Mpz::zero() => {
+                    RawValue::Ratio(gmp::mpq::Mpq::ratio(n, d))
+                }
+
+                _ => return Err(ImageError::Format("invalid rational"))
+            }
+        }
+
+        t if t == VAL_CHAR => {
+            let c = try!(read_be_u64(inp));
+            match ::std::char::from_u32(c as u32) {
+                Some(ch) if ch as u64 == c => RawValue::Char(ch),
+                _ => return Err(ImageError::Format("invalid character"))
+            }
+        }
+
+        t if t == VAL_FLONUM => RawValue::Flonum(f64::from_bits(try!(read_be_u64(inp)))),
+        t if t == VAL_FIXNUM => RawValue::Fixnum(try!(read_be_u64(inp)) as i64),
+        t if t == VAL_PRIMITIVE => RawValue::Primitive(try!(read_str(inp))),
+        t if t == VAL_PAIR || t == VAL_CLOSURE ||
+             t == VAL_STRING || t == VAL_SYMBOL ||
+             t == VAL_VECTOR || t == VAL_BYTEVECTOR || t == VAL_HASHTABLE => {
+            RawValue::Ref(t, try!(read_be_u64(inp)))
+        }
+
+        _ => return Err(ImageError::Format("unknown value tag"))
+    })
+}
+
+fn read_raw_obj<R: Read>(inp: &mut R, tag: u8) -> Result<RawObj, ImageError> {
+    Ok(match tag {
+        t if t == OBJ_STRING => {
+            let mutable = try!(read_u8(inp)) != 0;
+            RawObj::String(mutable, try!(read_str(inp)))
+        }
+
+        t if t == OBJ_PAIR => {
+            let car = try!(read_raw_value(inp));
+            RawObj::Pair(car, try!(read_raw_value(inp)))
+        }
+
+        t if t == OBJ_ENV => {
+            let size = try!(read_be_u64(inp));
+
+            let mut values = vec!();
+            for _ in 0 .. size {
+                let d = try!(read_u8(inp)) != 0;
+                values.push((d, try!(read_raw_value(inp))));
+            }
+
+            let next = match try!(read_u8(inp)) {
+                0 => None,
+                _ => Some(try!(read_be_u64(inp)))
+            };
+
+            RawObj::Env(values, next)
+        }
+
+        t if t == OBJ_CLOSURE => {
+            let pc = try!(read_be_u64(inp));
+            let env = try!(read_be_u64(inp));
+            let arity = try!(read_u8(inp));
+            RawObj::Closure(pc, env, arity, try!(read_u8(inp)) != 0)
+        }
+
+        t if t == OBJ_VECTOR => {
+            let mutable = try!(read_u8(inp)) != 0;
+            let len = try!(read_be_u64(inp));
+            let mut values = vec!();
+
+            for _ in 0 .. len {
+                values.push(try!(read_raw_value(inp)));
+            }
+
+            RawObj::Vector(mutable, values)
+        }
+
+        t if t == OBJ_BYTEVECTOR => {
+            let mutable = try!(read_u8(inp)) != 0;
+            let len = try!(read_be_u64(inp));
+            let mut bytes = vec!();
+            try!(inp.take(len).read_to_end(&mut bytes));
+
+            if bytes.len() as u64 != len {
+                return Err(ImageError::Format("truncated bytevector"))
+            }
+
+            RawObj::Bytevector(mutable, bytes)
+        }
+
+        t if t == OBJ_HASHTABLE => {
+            let equiv = match try!(read_u8(inp)) {
+                0 => gc::Equiv::Eq,
+                1 => gc::Equiv::Eqv,
+                2 => gc::Equiv::Equal,
+                3 => gc::Equiv::String,
+                _ => return Err(ImageError::Format("unknown hash table equivalence"))
+            };
+
+            let len = try!(read_be_u64(inp));
+            let mut entries = vec!();
+
+            for _ in 0 .. len {
+                let k = try!(read_raw_value(inp));
+                entries.push((k, try!(read_raw_value(inp))));
+            }
+
+            RawObj::HashTable(equiv, entries)
+        }
+
+        _ => return Err(ImageError::Format("unknown object tag"))
+    })
+}
+
+fn read_ids<R: Read>(inp: &mut R) -> Result<Vec<u64>, ImageError> {
+    let count = try!(read_be_u64(inp));
+    let mut ids = vec!();
+
+    for _ in 0 .. count {
+        ids.push(try!(read_be_u64(inp)));
+    }
+
+    Ok(ids)
+}
+
+// resolves references to the objects of an image, once they are allocated
+
+struct Heap {
+    objs: Vec<Option<Obj>>
+}
+
+impl Heap {
+    fn get(&self, id: u64) -> Option<Obj> {
+        self.objs.get(id as usize).and_then(|o| *o)
+    }
+
+    fn string(&self, id: u64) -> Result<gc::Ptr<gc::String>, ImageError> {
+        match self.get(id) {
+            Some(Obj::String(s)) => Ok(s),
+            _ => Err(ImageError::Format("bad string reference"))
+        }
+    }
+
+    fn env(&self, id: u64) -> Result<gc::Ptr<gc::Env>, ImageError> {
+        match self.get(id) {
+            Some(Obj::Env(e)) => Ok(e),
+            _ => Err(ImageError::Format("bad environment reference"))
+        }
+    }
+
+    fn value(&self, v: RawValue) -> Result<gc::Value, ImageError> {
+        Ok(match v {
+            RawValue::Unit => value::Unit,
+            RawValue::Null => value::Null,
+            RawValue::Bool(b) => value::Bool(b),
+            RawValue::Char(c) => value::Char(c),
+            RawValue::Fixnum(i) => value::Fixnum(i),
+            RawValue::Flonum(f) => value::Flonum(f),
+            RawValue::Num(n) => primitives::num::from_mpz(n),
+            RawValue::Ratio(q) => primitives::num::from_mpq(q),
+            RawValue::Primitive(name) => match primitives::lookup(&name) {
+                Some(d) => value::Primitive(d.fun, d.name),
+                None => return Err(ImageError::Format("unknown primitive"))
+            },
+
+            RawValue::Ref(t, id) => match (t, self.get(id)) {
+                (t, Some(Obj::Pair(p))) if t == VAL_PAIR => value::Pair(p),
+                (t, Some(Obj::Closure(c))) if t == VAL_CLOSURE => value::Closure(c),
+                (t, Some(Obj::String(s))) if t == VAL_STRING => value::String(s),
+                (t, Some(Obj::String(s))) if t == VAL_SYMBOL => value::Symbol(s),
+                (t, Some(Obj::Vector(v))) if t == VAL_VECTOR => value::Vector(v),
+                (t, Some(Obj::Bytevector(bv))) if t == VAL_BYTEVECTOR => value::Bytevector(bv),
+                (t, Some(Obj::HashTable(h))) if t == VAL_HASHTABLE => value::HashTable(h),
+                _ => return Err(ImageError::Format("bad reference"))
+            }
+        })
+    }
+}
+
+impl VM {
+    pub fn dump_image(&mut self, path: &Path) -> Result<(), ImageError> {
+        let f = try!(fs::File::create(path));
+        let mut d = Dumper {
+            out: io::BufWriter::new(f),
+            ids: HashMap::new(),
+            queue: VecDeque::new()
+        };
+
+        try!(d.out.write_all(MAGIC));
+        try!(write_u8(&mut d.out, VERSION));
+
+        let interned = self.gc.interned();
+        try!(d.write_ids(&interned));
+
+        try!(write_be_u64(&mut d.out, self.modules.len() as u64));
+        for l in self.modules.iter() {
+            try!(write_libname(&mut d.out, &l.name));
+            try!(write_be_u64(&mut d.out, l.prog.len() as u64));
+            try!(d.out.write_all(&l.prog));
+            try!(d.write_id(Obj::Env(l.env)));
+            try!(d.write_ids(&l.sym_table));
+            try!(write_be_u64(&mut d.out, l.exports));
+            try!(d.write_ids(&l.export_names));
+            try!(write_debug(&mut d.out, &l.debug));
+
+            try!(write_be_u64(&mut d.out, l.consts.len() as u64));
+            for c in l.consts.iter() {
+                try!(d.write_value(c));
+            }
+
+            try!(write_be_u64(&mut d.out, l.prims.len() as u64));
+            for p in l.prims.iter() {
+                try!(d.write_value(p));
+            }
+        }
+
+        // the main program can't be imported
+        let main = LibName(vec!(format!("main")));
+        let loaded: Vec<(&LibName, &usize)> = self.loaded_mods.iter()
+            .filter(|&(n, _)| *n != main).collect();
+
+        try!(write_be_u64(&mut d.out, loaded.len() as u64));
+        for &(name, idx) in loaded.iter() {
+            try!(write_libname(&mut d.out, name));
+            try!(write_be_u64(&mut d.out, *idx as u64));
+        }
+
+        while let Some(obj) = d.queue.pop_front() {
+            try!(d.write_obj(obj));
+        }
+
+        try!(write_u8(&mut d.out, OBJ_END));
+        try!(d.out.flush());
+        Ok(())
+    }
+
+    // restore an image into a VM that hasn't loaded any library yet
+    pub fn load_image(&mut self, path: &Path) -> Result<(), ImageError> {
+        if !self.modules.is_empty() {
+            return Err(ImageError::Format("libraries already loaded"))
+        }
+
+        let f = try!(fs::File::open(path));
+        let mut inp = io::BufReader::new(f);
+
+        let mut magic = [0u8; 3];
+        try!(inp.read_exact(&mut magic));
+        if &magic[..] != MAGIC || try!(read_u8(&mut inp)) != VERSION {
+            return Err(ImageError::Format("not an image"))
+        }
+
+        let interned = try!(read_ids(&mut inp));
+
+        let mod_count = try!(read_be_u64(&mut inp));
+        let mut libs = vec!();
+        for _ in 0 .. mod_count {
+            let name = try!(read_libname(&mut inp));
+            let len = try!(read_be_u64(&mut inp));
+            let mut prog = vec!();
+            try!((&mut inp).take(len).read_to_end(&mut prog));
+
+            if prog.len() as u64 != len {
+                return Err(ImageError::Format("truncated program"))
+            }
+
+            let env = try!(read_be_u64(&mut inp));
+            let sym_table = try!(read_ids(&mut inp));
+            let exports = try!(read_be_u64(&mut inp));
+            let export_names = try!(read_ids(&mut inp));
+            let debug = try!(read_debug(&mut inp));
+
+            let count = try!(read_be_u64(&mut inp));
+            let mut consts = vec!();
+            for _ in 0 .. count {
+                consts.push(try!(read_raw_value(&mut inp)));
+            }
+
+            let count = try!(read_be_u64(&mut inp));
+            let mut prims = vec!();
+            for _ in 0 .. count {
+                prims.push(try!(read_raw_value(&mut inp)));
+            }
+
+            libs.push(RawLibrary {
+                name: name, prog: prog, env: env, sym_table: sym_table,
+                exports: exports, export_names: export_names, debug: debug,
+                consts: consts, prims: prims
+            });
+        }
+
+        let loaded_count = try!(read_be_u64(&mut inp));
+        let mut loaded = vec!();
+        for _ in 0 .. loaded_count {
+            let name = try!(read_libname(&mut inp));
+            let idx = try!(read_be_u64(&mut inp)) as usize;
+
+            if idx >= libs.len() {
+                return Err(ImageError::Format("bad library index"))
+            }
+
+            loaded.push((name, idx));
+        }
+
+        let mut raw = vec!();
+        loop {
+            let tag = try!(read_u8(&mut inp));
+            if tag == OBJ_END { break }
+            raw.push(try!(read_raw_obj(&mut inp, tag)));
+        }
+
+        // first allocate all the objects, then fill them once every
+        // reference can be resolved. No collection happens meanwhile
+        let mut heap = Heap { objs: vec!() };
+
+        for o in raw.iter() {
+            heap.objs.push(match o {
+                // immutable strings are interned again
+                &RawObj::String(false, ref s) => Some(Obj::String(self.gc.intern(s.clone()))),
+                &RawObj::String(true, ref s) => Some(Obj::String(self.gc.alloc(gc::String {
+                    str: s.clone(),
+                    mutable: true
+                }))),
+
+                &RawObj::Pair(_, _) => Some(Obj::Pair(self.gc.alloc(gc::Pair {
+                    car: value::Unit,
+                    cdr: value::Null
+                }))),
+
+                &RawObj::Env(ref values, _) => Some(Obj::Env(self.gc.alloc(gc::Env {
+                    values: Vec::with_capacity(values.len()),
+                    next: None
+                }))),
+
+                &RawObj::Vector(mutable, ref values) => Some(Obj::Vector(self.gc.alloc(gc::Vector {
+                    values: Vec::with_capacity(values.len()),
+                    mutable: mutable
+                }))),
+
+                &RawObj::Bytevector(mutable, ref bytes) => {
+                    let bv = gc::Bytevector { bytes: bytes.clone(), mutable: mutable };
+                    Some(Obj::Bytevector(self.gc.alloc(bv)))
+                }
+
+                &RawObj::HashTable(equiv, _) => {
+                    Some(Obj::HashTable(self.gc.alloc(gc::HashTable::new(equiv))))
+                }
+
+                // closures are allocated once environments are
+                &RawObj::Closure(..) => None
+            });
+        }
+
+        for (i, o) in raw.iter().enumerate() {
+            match o {
+                &RawObj::Closure(pc, env, arity, variadic) => {
+                    let env = try!(heap.env(env));
+                    heap.objs[i] = Some(Obj::Closure(self.gc.alloc(gc::Closure {
+                        pc: pc, env: env, arity: arity, variadic: variadic
+                    })));
+                }
+
+                _ => ()
+            }
+        }
+
+        // hash tables are filled last, once the keys they hash are
+        let mut entries = vec!();
+
+        for (i, o) in raw.into_iter().enumerate() {
+            match (o, heap.objs[i]) {
+                (RawObj::Pair(car, cdr), Some(Obj::Pair(mut p))) => {
+                    p.car = try!(heap.value(car));
+                    p.cdr = try!(heap.value(cdr));
+                }
+
+                (RawObj::Vector(_, values), Some(Obj::Vector(mut vec))) => {
+                    for v in values.into_iter() {
+                        let v = try!(heap.value(v));
+                        vec.values.push(v);
+                    }
+                }
+
+                (RawObj::Env(values, next), Some(Obj::Env(mut e))) => {
+                    for (d, v) in values.into_iter() {
+                        let v = try!(heap.value(v));
+                        e.values.push((d, v));
+                    }
+
+                    e.next = match next {
+                        Some(id) => Some(try!(heap.env(id))),
+                        None => None
+                    };
+                }
+
+                (RawObj::HashTable(_, kvs), Some(Obj::HashTable(t))) => {
+                    for (k, v) in kvs.into_iter() {
+                        let k = try!(heap.value(k));
+                        entries.push((t, k, try!(heap.value(v))));
+                    }
+                }
+
+                _ => ()
+            }
+        }
+
+        for &mut (ref mut t, ref k, ref v) in entries.iter_mut() {
+            t.insert(k, v);
+        }
+
+        // interned strings were interned again when allocated
+        for id in interned.iter() {
+            try!(heap.string(*id));
+        }
+
+        for l in libs.into_iter() {
+            let env = try!(heap.env(l.env));
+            let mut sym_table = vec!();
+            for id in l.sym_table.iter() {
+                sym_table.push(try!(heap.string(*id)));
+            }
+
+            let mut export_names = vec!();
+            for id in l.export_names.iter() {
+                export_names.push(try!(heap.string(*id)));
+            }
+
+            let mut consts = vec!();
+            for c in l.consts.into_iter() {
+                consts.push(try!(heap.value(c)));
+            }
+
+            let mut prims = vec!();
+            for p in l.prims.into_iter() {
+                prims.push(try!(heap.value(p)));
+            }
+
+            let lib = Box::new(Library {
+                name: Box::new(l.name), prog: l.prog, env: env,
+                imports: vec!(), sym_table: sym_table,
+                exports: l.exports, export_names: export_names,
+                debug: l.debug, consts: consts, prims: prims
+            });
+
+            if lib.check_text().is_err() {
+                return Err(ImageError::Format("invalid library text"))
+            }
+
+            self.modules.push(lib);
+        }
+
+        for (name, idx) in loaded.into_iter() {
+            self.loaded_mods.insert(name, idx);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+use std::io;
+use std::io::Write;
+use std::process;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::atomic::ATOMIC_BOOL_INIT;
+
+use gc::value;
+use libc;
+use vm::VM;
+
+// Asynchronous interrupts (Ctrl-C)
+// the signal handler only sets a flag, which the dispatch loop checks
+// between two instructions, where the VM is in a consistent state
+// there is a single flag for the whole program, only the root VM checks
+// it, spawned processes are not interrupted
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OnInterrupt {
+    // enter the interactive debugger
+    Debug,
+    // print a backtrace and exit
+    Backtrace,
+    // raise an interrupt condition, that can be caught by Scheme code
+    Raise
+}
+
+static INTERRUPTED: AtomicBool = ATOMIC_BOOL_INIT;
+
+extern fn on_sigint(_: libc::c_int) {
+    // a second Ctrl-C before the VM reached a safe point (e.g. stuck
+    // in a primitive) kills the process
+    if INTERRUPTED.swap(true, Ordering::SeqCst) {
+        unsafe { libc::_exit(130) }
+    }
+}
+
+// install the SIGINT handler. Until it is called, Ctrl-C kills
+// the process as usual
+#[cfg(unix)]
+pub fn install() {
+    unsafe {
+        libc::signal(libc::SIGINT, on_sigint as libc::sighandler_t);
+    }
+}
+
+#[cfg(not(unix))]
+pub fn install() {
+}
+
+#[inline(always)]
+pub fn pending() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+impl VM {
+    // handle a pending interrupt, must only be called at safe points
+    pub fn interrupt(&mut self) {
+        if !INTERRUPTED.swap(false, Ordering::SeqCst) {
+            return
+        }
+
+        match self.on_interrupt {
+            OnInterrupt::Debug => self.debugger(),
+
+            OnInterrupt::Backtrace => {
+                let mut err = io::stderr();
+                let _ = writeln!(err, "Interrupted");
+                let _ = write!(err, "{}", self.backtrace());
+                process::exit(130);
+            }
+
+            OnInterrupt::Raise => {
+                let msg = "interrupted by user".to_string();
+                self.error("interrupt", msg, value::Null);
+            }
+        }
+    }
+}
@@ -1,14 +1,71 @@
+use std::env;
+use std::fmt;
 use std::fs;
 use std::io;
 use std::io::Read;
-use std::io::Seek;
 use std::path::Path;
 use std::path::PathBuf;
 use std::slice::Iter;
 
+use common::bytecode;
 use gc;
-
-static DEFAULT_PREFIX: &'static str = "/usr/local/";
+use gc::value;
+use gmp;
+use primitives;
+use vm::disasm;
+use vm::debuginfo::DebugInfo;
+use vm::debuginfo::Location;
+use vm::debuginfo::Procedure;
+
+static DEFAULT_PREFIX: &'static str = "/usr/local/lib/scmrun/";
+
+// colon-separated list of directories searched for libraries
+static PATH_VAR: &'static str = "SCMRUN_LIBRARY_PATH";
+
+// extension of compiled library files
+static EXTENSION: &'static str = "bin";
+
+// Version 2 files start with a 20 bytes header: the magic number, the
+// version, the flags, 3 reserved bytes, the number of sections and the
+// Adler-32 checksum of the rest of the file. It is followed by the section
+// table, where each section is described by its kind, offset and length
+// as big-endian u64s. The contents of the sections are the same as in
+// version 1, where the header gives the offsets of the 4 sections after
+// 27 reserved bytes.
+
+// magic number of version 2 files
+static MAGIC: &'static [u8] = b"SCB";
+
+// kinds of the sections of version 2 files
+static SECTION_SYMTAB: u64 = 0x01;
+static SECTION_IMPORTS: u64 = 0x02;
+static SECTION_EXPORTS: u64 = 0x03;
+static SECTION_TEXT: u64 = 0x04;
+// optional: the source file name, the line table as (offset, line,
+// column), the procedures as (entry, end, name, parameters), and the
+// variable names of each Alloc as (offset, names)
+static SECTION_DEBUG: u64 = 0x05;
+// the constants pushed by Push Const, see Reader::consts
+static SECTION_CONSTS: u64 = 0x06;
+// the names of the primitives pushed by Push Prim
+static SECTION_PRIMS: u64 = 0x07;
+
+// kinds of constants
+static CONST_UNIT: u8 = 0x00;
+static CONST_BOOL: u8 = 0x01;
+static CONST_INT: u8 = 0x02;
+static CONST_CHAR: u8 = 0x03;
+static CONST_STRING: u8 = 0x04;
+static CONST_SYM: u8 = 0x05;
+static CONST_LIST: u8 = 0x06;
+static CONST_NULL: u8 = 0x07;
+static CONST_FLONUM: u8 = 0x08;
+static CONST_VECTOR: u8 = 0x09;
+static CONST_BYTEVECTOR: u8 = 0x0A;
+
+// flags of the byte following the version
+// set if exports are named and imports are import sets
+static FLAG_NAMED: u8 = 0x01;
 
 #[derive(Eq, Clone, Hash, PartialEq)]
 pub struct LibName(pub Vec<String>);
@@ -18,161 +75,894 @@ impl LibName {
         let &LibName(ref vec) = self;
         vec.iter()
     }
+
+    // the path of the library file, relative to a directory of the
+    // search path: (foo bar) is found in foo/bar.bin
+    pub fn file_name(&self) -> PathBuf {
+        let mut p = PathBuf::new();
+
+        for part in self.iter() {
+            p.push(part);
+        }
+
+        p.set_extension(EXTENSION);
+        p
+    }
+}
+
+impl fmt::Display for LibName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parts: Vec<&str> = self.iter().map(|s| &s[..]).collect();
+        write!(f, "({})", parts.join(" "))
+    }
+}
+
+pub enum LoadError {
+    Io(PathBuf, io::Error),
+    // the library name, and every file that was tried
+    NotFound(LibName, Vec<PathBuf>),
+    // an import set refers to a library without export names
+    Unnamed(LibName),
+    // a malformed library file
+    Format(PathBuf, String),
+    // a primitive this VM doesn't have
+    UnknownPrimitive(PathBuf, String),
+    // an identifier missing from an import set
+    UnknownName(LibName, String),
+    // an exported slot beyond the environment of the library
+    MissingSlot(LibName, u64),
+    // libraries importing each other, the first one is repeated last
+    Cycle(Vec<LibName>)
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &LoadError::Io(ref p, ref e) => {
+                write!(f, "cannot read {}: {}", p.display(), e)
+            }
+
+            &LoadError::NotFound(ref name, ref tried) => {
+                try!(write!(f, "library {} not found, tried:", name));
+
+                for p in tried.iter() {
+                    try!(write!(f, "\n  {}", p.display()));
+                }
+
+                Ok(())
+            }
+
+            &LoadError::Format(ref p, ref msg) => {
+                write!(f, "invalid library file {}: {}", p.display(), msg)
+            }
+
+            &LoadError::UnknownPrimitive(ref p, ref prim) => {
+                write!(f, "{} uses the unknown primitive {}", p.display(), prim)
+            }
+
+            &LoadError::Unnamed(ref name) => {
+                write!(f, "library {} has no named exports, it can only be \
+                           imported as a whole", name)
+            }
+
+            &LoadError::UnknownName(ref name, ref id) => {
+                write!(f, "identifier {} is not in the import set of {}", id, name)
+            }
+
+            &LoadError::MissingSlot(ref name, slot) => {
+                write!(f, "library {} has no variable in slot {}", name, slot)
+            }
+
+            &LoadError::Cycle(ref cycle) => {
+                let path: Vec<String> = cycle.iter().map(|n| n.to_string()).collect();
+                write!(f, "cyclic library imports: {}", path.join(" -> "))
+            }
+        }
+    }
+}
+
+// R7RS import set modifiers, applied in order to the exports
+// of the imported library
+
+#[derive(Clone)]
+pub enum ImportMod {
+    Only(Vec<gc::Ptr<gc::String>>),
+    Except(Vec<gc::Ptr<gc::String>>),
+    Prefix(gc::Ptr<gc::String>),
+    Rename(Vec<(gc::Ptr<gc::String>, gc::Ptr<gc::String>)>)
+}
+
+#[derive(Clone)]
+pub struct Import {
+    pub lib: Box<LibName>,
+    pub mods: Vec<ImportMod>,
+    // the names bound by the importer, in the order of its environment,
+    // or nothing to bind the whole import set
+    pub bindings: Vec<gc::Ptr<gc::String>>
 }
 
-// FIXME: for some reason this requires an impl of std::str::Str
-// for LibName
-// impl fmt::String for LibName {
-//    fn fmt(obj: &LibName, f: &mut fmt::Formatter) {
-//        write!(f.buf, "( ");
-//
-//        for part in obj.iter() {
-//            write!(f.buf, "{:s} ", *part);
-//        }
-//
-//        write!(f.buf, ")");
-//    }
-// }
+impl Import {
+    fn positional(lib: Box<LibName>) -> Import {
+        Import { lib: lib, mods: vec!(), bindings: vec!() }
+    }
+
+    // returns the indexes in the environment of the imported library of
+    // the values bound by this import
+    pub fn resolve(&self, gc: &mut gc::GC, lib: &Library) -> Result<Vec<u64>, LoadError> {
+        if lib.export_names.is_empty() {
+            if self.mods.is_empty() && self.bindings.is_empty() {
+                return Ok((0 .. lib.exports).collect())
+            }
+
+            return Err(LoadError::Unnamed(*self.lib.clone()))
+        }
+
+        let mut set: Vec<(gc::Ptr<gc::String>, u64)> = lib.export_names.iter()
+            .enumerate().map(|(i, n)| (*n, i as u64)).collect();
+
+        for m in self.mods.iter() {
+            match m {
+                &ImportMod::Only(ref ids) => {
+                    try!(self.check(&set, ids.iter()));
+                    set.retain(|&(n, _)| ids.contains(&n));
+                }
+
+                &ImportMod::Except(ref ids) => {
+                    try!(self.check(&set, ids.iter()));
+                    set.retain(|&(n, _)| !ids.contains(&n));
+                }
+
+                &ImportMod::Prefix(p) => {
+                    for &mut (ref mut n, _) in set.iter_mut() {
+                        *n = gc.intern(format!("{}{}", p.str, n.str));
+                    }
+                }
+
+                &ImportMod::Rename(ref ids) => {
+                    try!(self.check(&set, ids.iter().map(|&(ref from, _)| from)));
+
+                    for &mut (ref mut n, _) in set.iter_mut() {
+                        match ids.iter().find(|&&(from, _)| from == *n) {
+                            Some(&(_, to)) => *n = to,
+                            None => ()
+                        }
+                    }
+                }
+            }
+        }
+
+        if self.bindings.is_empty() {
+            return Ok(set.iter().map(|&(_, i)| i).collect())
+        }
+
+        let mut slots = Vec::with_capacity(self.bindings.len());
+        for b in self.bindings.iter() {
+            match set.iter().find(|&&(n, _)| n == *b) {
+                Some(&(_, i)) => slots.push(i),
+                None => return Err(self.unknown(*b))
+            }
+        }
+
+        Ok(slots)
+    }
+
+    fn check<'a, I>(&self, set: &[(gc::Ptr<gc::String>, u64)], ids: I) -> Result<(), LoadError>
+        where I: Iterator<Item=&'a gc::Ptr<gc::String>> {
+        for id in ids {
+            if !set.iter().any(|&(n, _)| n == *id) {
+                return Err(self.unknown(*id))
+            }
+        }
+
+        Ok(())
+    }
+
+    fn unknown(&self, id: gc::Ptr<gc::String>) -> LoadError {
+        LoadError::UnknownName(*self.lib.clone(), id.str.clone())
+    }
+}
 
 pub struct Library {
     pub name: Box<LibName>,
     pub prog: Vec<u8>,
     pub env: gc::Ptr<gc::Env>,
 
-    pub imports: Vec<Box<LibName>>,
+    pub imports: Vec<Import>,
     pub sym_table: Vec<gc::Ptr<gc::String>>,
-    pub exports: u64
+    pub exports: u64,
+    // names of the exported values, if the library has them
+    pub export_names: Vec<gc::Ptr<gc::String>>,
+    pub debug: Option<DebugInfo>,
+    // literals of the program, built when the library is loaded
+    // strings, vectors and bytevectors are immutable, but nothing
+    // prevents set-car! on the pairs of quoted lists yet
+    pub consts: Vec<gc::Value>,
+    // primitives linked by name
+    pub prims: Vec<gc::Value>
+}
+
+// a bounds-checked reader over a section of a library file
+// every error tells what was being read, and where
+
+struct Reader<'a> {
+    data: &'a [u8],
+    // offset of data in the file
+    base: usize,
+    pos: usize,
+    what: &'static str
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8], what: &'static str) -> Reader<'a> {
+        Reader { data: data, base: 0, pos: 0, what: what }
+    }
+
+    // the part of the file starting at off, of the given length
+    fn section(file: &'a [u8], what: &'static str,
+               off: u64, len: u64) -> Result<Reader<'a>, String> {
+        match off.checked_add(len) {
+            Some(end) if end <= file.len() as u64 => Ok(Reader {
+                data: &file[off as usize .. end as usize],
+                base: off as usize, pos: 0, what: what
+            }),
+
+            _ => Err(format!("{} at offset {:#x} (length {:#x}) is past the end \
+                              of the file ({:#x} bytes)", what, off, len, file.len()))
+        }
+    }
+
+    fn error(&self, msg: String) -> String {
+        format!("{} at offset {:#x}: {}", self.what, self.base + self.pos, msg)
+    }
+
+    fn bytes(&mut self, n: u64) -> Result<&'a [u8], String> {
+        let left = (self.data.len() - self.pos) as u64;
+
+        if n > left {
+            return Err(self.error(format!("truncated, {} bytes expected, {} left",
+                                          n, left)))
+        }
+
+        let ret = &self.data[self.pos .. self.pos + n as usize];
+        self.pos += n as usize;
+        Ok(ret)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(try!(self.bytes(1))[0])
+    }
+
+    fn be_u32(&mut self) -> Result<u32, String> {
+        let b = try!(self.bytes(4));
+        Ok(b.iter().fold(0, |n, b| n << 8 | *b as u32))
+    }
+
+    fn be_u64(&mut self) -> Result<u64, String> {
+        let b = try!(self.bytes(8));
+        Ok(b.iter().fold(0, |n, b| n << 8 | *b as u64))
+    }
+
+    // the number of items of a table, each taking at least item_size
+    // bytes, which must fit in what is left of the section
+    fn count(&mut self, item_size: u64) -> Result<u64, String> {
+        let n = try!(self.be_u64());
+        let left = (self.data.len() - self.pos) as u64;
+
+        if n > left / item_size {
+            self.pos -= 8;
+            return Err(self.error(format!("count {} exceeds the size of the section", n)))
+        }
+
+        Ok(n)
+    }
+
+    // strings are stored in UTF-8
+    fn string(&mut self) -> Result<String, String> {
+        let len = try!(self.count(1));
+        let bytes = try!(self.bytes(len)).to_vec();
+
+        match String::from_utf8(bytes) {
+            Ok(s) => Ok(s),
+            Err(_) => {
+                self.pos -= len as usize;
+                Err(self.error(format!("invalid UTF-8 string")))
+            }
+        }
+    }
+
+    // a symbol, as an index in the symbol table
+    fn sym(&mut self, symt: &[gc::Ptr<gc::String>]) -> Result<gc::Ptr<gc::String>, String> {
+        let i = try!(self.be_u64());
+
+        match symt.get(i as usize) {
+            Some(s) => Ok(*s),
+            None => {
+                self.pos -= 8;
+                Err(self.error(format!("symbol {} is not in the symbol table \
+                                        ({} symbols)", i, symt.len())))
+            }
+        }
+    }
+
+    fn syms(&mut self, symt: &[gc::Ptr<gc::String>]) -> Result<Vec<gc::Ptr<gc::String>>, String> {
+        let count = try!(self.count(8));
+        let mut syms = vec!();
+
+        for _ in 0 .. count {
+            syms.push(try!(self.sym(symt)));
+        }
+
+        Ok(syms)
+    }
+
+    fn import_mods(&mut self, symt: &[gc::Ptr<gc::String>]) -> Result<Vec<ImportMod>, String> {
+        let count = try!(self.count(1));
+        let mut mods = vec!();
+
+        for _ in 0 .. count {
+            mods.push(match try!(self.u8()) {
+                0x00 => ImportMod::Only(try!(self.syms(symt))),
+                0x01 => ImportMod::Except(try!(self.syms(symt))),
+                0x02 => ImportMod::Prefix(try!(self.sym(symt))),
+                0x03 => {
+                    let n = try!(self.count(16));
+                    let mut ids = vec!();
+
+                    for _ in 0 .. n {
+                        let from = try!(self.sym(symt));
+                        ids.push((from, try!(self.sym(symt))));
+                    }
+
+                    ImportMod::Rename(ids)
+                }
+
+                m => {
+                    self.pos -= 1;
+                    return Err(self.error(format!("unknown import set modifier {:#x}", m)))
+                }
+            });
+        }
+
+        Ok(mods)
+    }
+
+    // an offset in the text of the library
+    fn text_off(&mut self, text_len: usize) -> Result<u32, String> {
+        let off = try!(self.be_u64());
+
+        if off > text_len as u64 {
+            self.pos -= 8;
+            return Err(self.error(format!("offset {:#x} is past the end of the text \
+                                           ({:#x} bytes)", off, text_len)))
+        }
+
+        Ok(off as u32)
+    }
+
+    fn strings(&mut self) -> Result<Vec<String>, String> {
+        let count = try!(self.count(8));
+        let mut strs = vec!();
+
+        for _ in 0 .. count {
+            strs.push(try!(self.string()));
+        }
+
+        Ok(strs)
+    }
+
+    // constants are a kind byte followed by their contents: a byte for
+    // booleans, a hexadecimal string for integers, the code point of
+    // characters as a u32, the IEEE 754 bits of flonums as a u64, a
+    // string, or an index in the symbol table for symbols. Lists give the
+    // indexes of their elements and of their tail, which must be earlier
+    // constants, vectors the indexes of their elements, and bytevectors
+    // their bytes
+    fn consts(&mut self, gc: &mut gc::GC,
+              symt: &[gc::Ptr<gc::String>]) -> Result<Vec<gc::Value>, String> {
+        let count = try!(self.count(1));
+        let mut consts: Vec<gc::Value> = vec!();
+
+        for _ in 0 .. count {
+            let kind = try!(self.u8());
+
+            let c = match kind {
+                k if k == CONST_UNIT => value::Unit,
+                k if k == CONST_NULL => value::Null,
+                k if k == CONST_BOOL => value::Bool(try!(self.u8()) != 0),
+                k if k == CONST_INT => {
+                    let s = try!(self.string());
+                    match gmp::mpz::Mpz::from_str_radix(&s, 16) {
+                        Ok(n) => primitives::num::from_mpz(n),
+                        Err(_) => return Err(self.error(format!("invalid integer {}", s)))
+                    }
+                }
+
+                k if k == CONST_CHAR => {
+                    let c = try!(self.be_u32());
+                    match ::std::char::from_u32(c) {
+                        Some(c) => value::Char(c),
+                        None => {
+                            self.pos -= 4;
+                            return Err(self.error(format!("invalid character {:#x}", c)))
+                        }
+                    }
+                }
+
+                k if k == CONST_FLONUM => value::Flonum(f64::from_bits(try!(self.be_u64()))),
+
+                // string literals are immutable
+                k if k == CONST_STRING => value::String(gc.intern(try!(self.string()))),
+                k if k == CONST_SYM => value::Symbol(try!(self.sym(symt))),
+                k if k == CONST_LIST => {
+                    let n = try!(self.count(8));
+                    let mut items = try!(self.const_refs(&consts, n + 1));
+
+                    let mut ret = items.pop().unwrap();
+                    for v in items.iter().rev() {
+                        ret = value::Pair(value::list::cons(v, &ret, gc));
+                    }
+
+                    ret
+                }
+
+                k if k == CONST_VECTOR => {
+                    let n = try!(self.count(8));
+                    let items = try!(self.const_refs(&consts, n));
+                    value::Vector(gc.alloc(gc::Vector { values: items, mutable: false }))
+                }
+
+                k if k == CONST_BYTEVECTOR => {
+                    let n = try!(self.count(1));
+                    let bytes = try!(self.bytes(n)).to_vec();
+                    let bv = gc::Bytevector { bytes: bytes, mutable: false };
+                    value::Bytevector(gc.alloc(bv))
+                }
+
+                k => {
+                    self.pos -= 1;
+                    return Err(self.error(format!("unknown constant kind {:#x}", k)))
+                }
+            };
+
+            consts.push(c);
+        }
+
+        Ok(consts)
+    }
+
+    // n indexes of constants read so far
+    fn const_refs(&mut self, consts: &[gc::Value],
+                  n: u64) -> Result<Vec<gc::Value>, String> {
+        let mut items = vec!();
+
+        for _ in 0 .. n {
+            let i = try!(self.be_u64());
+
+            match consts.get(i as usize) {
+                Some(c) => items.push(c.clone()),
+                None => {
+                    self.pos -= 8;
+                    return Err(self.error(format!("constant {} is not \
+                                                   defined yet", i)))
+                }
+            }
+        }
+
+        Ok(items)
+    }
+
+    // the source file, the line table, the procedures, and the
+    // variables of each Alloc
+    fn debug_info(&mut self, text_len: usize) -> Result<DebugInfo, String> {
+        let file = try!(self.string());
+
+        let count = try!(self.count(24));
+        let mut lines: Vec<(u32, Location)> = vec!();
+        for _ in 0 .. count {
+            let off = try!(self.text_off(text_len));
+            let line = try!(self.be_u64());
+            let column = try!(self.be_u64());
+
+            match lines.last() {
+                Some(&(o, _)) if o >= off => {
+                    return Err(self.error(format!("line table is not sorted")))
+                }
+
+                _ => ()
+            }
+
+            lines.push((off, Location { line: line, column: column }));
+        }
+
+        let count = try!(self.count(32));
+        let mut procs = vec!();
+        for _ in 0 .. count {
+            let entry = try!(self.text_off(text_len));
+            let end = try!(self.text_off(text_len));
+            let name = try!(self.string());
+            let params = try!(self.strings());
+            procs.push(Procedure { entry: entry, end: end, name: name, params: params });
+        }
+
+        let count = try!(self.count(16));
+        let mut locals = vec!();
+        for _ in 0 .. count {
+            let off = try!(self.text_off(text_len));
+            locals.push((off, try!(self.strings())));
+        }
+
+        Ok(DebugInfo { file: file, lines: lines, procs: procs, locals: locals })
+    }
 }
 
-// WHY THE FUCK IS THIS NOT IN STDLIB
-fn read_be_u64<T>(file: &mut T) -> io::Result<u64> where T: Read {
-    let mut buf = 0u64;
-    try!(unsafe {
-        file.read_exact(::std::slice::from_raw_parts_mut(
-                &mut buf as *mut u64 as *mut u8, 8))
-    });
-    Ok(u64::from_be(buf))
+// Adler-32 checksum of the sections of a version 2 file
+
+fn adler32(data: &[u8]) -> u32 {
+    let mut a = 1u32;
+    let mut b = 0u32;
+
+    for chunk in data.chunks(5552) {
+        for byte in chunk.iter() {
+            a += *byte as u32;
+            b += a;
+        }
+
+        a %= 65521;
+        b %= 65521;
+    }
+
+    b << 16 | a
+}
+
+// the sections of a library file
+
+struct Sections<'a> {
+    symtab: Reader<'a>,
+    imports: Reader<'a>,
+    exports: Reader<'a>,
+    text: Reader<'a>,
+    debug: Option<Reader<'a>>,
+    consts: Option<Reader<'a>>,
+    prims: Option<Reader<'a>>
+}
+
+impl<'a> Sections<'a> {
+    // version 1 files only give the offsets of the sections, each of
+    // them extends to the end of the file
+    fn v1(file: &'a [u8], h: &mut Reader<'a>) -> Result<Sections<'a>, String> {
+        let mut sections = vec!();
+
+        for what in ["symbol table", "imports", "exports", "text"].iter() {
+            let off = try!(h.be_u64());
+
+            if off > file.len() as u64 {
+                h.pos -= 8;
+                return Err(h.error(format!("{} section offset {:#x} is past \
+                                            the end of the file", what, off)))
+            }
+
+            sections.push(try!(Reader::section(file, what, off, file.len() as u64 - off)));
+        }
+
+        let text = sections.pop().unwrap();
+        let exports = sections.pop().unwrap();
+        let imports = sections.pop().unwrap();
+        let symtab = sections.pop().unwrap();
+        Ok(Sections {
+            symtab: symtab, imports: imports, exports: exports,
+            text: text, debug: None, consts: None, prims: None
+        })
+    }
+
+    // version 2 files have a checksummed table of (kind, offset, length)
+    fn v2(file: &'a [u8], h: &mut Reader<'a>) -> Result<Sections<'a>, String> {
+        let count = try!(h.count(24));
+        let checksum = try!(h.be_u32());
+
+        let sum = adler32(&file[h.pos ..]);
+        if sum != checksum {
+            return Err(format!("checksum mismatch: header says {:#010x}, contents \
+                                give {:#010x}", checksum, sum))
+        }
+
+        let mut symtab = None;
+        let mut imports = None;
+        let mut exports = None;
+        let mut text = None;
+        let mut debug = None;
+        let mut consts = None;
+        let mut prims = None;
+
+        for _ in 0 .. count {
+            let kind = try!(h.be_u64());
+            let off = try!(h.be_u64());
+            let len = try!(h.be_u64());
+
+            let (what, slot) = match kind {
+                k if k == SECTION_SYMTAB => ("symbol table", &mut symtab),
+                k if k == SECTION_IMPORTS => ("imports", &mut imports),
+                k if k == SECTION_EXPORTS => ("exports", &mut exports),
+                k if k == SECTION_TEXT => ("text", &mut text),
+                k if k == SECTION_DEBUG => ("debug", &mut debug),
+                k if k == SECTION_CONSTS => ("constants", &mut consts),
+                k if k == SECTION_PRIMS => ("primitives", &mut prims),
+                // sections unknown to this version are skipped
+                _ => continue
+            };
+
+            if slot.is_some() {
+                return Err(h.error(format!("duplicate {} section", what)))
+            }
+
+            *slot = Some(try!(Reader::section(file, what, off, len)));
+        }
+
+        fn required<'a>(s: Option<Reader<'a>>, what: &str) -> Result<Reader<'a>, String> {
+            s.ok_or(format!("missing {} section", what))
+        }
+
+        Ok(Sections {
+            symtab: try!(required(symtab, "symbol table")),
+            imports: try!(required(imports, "imports")),
+            exports: try!(required(exports, "exports")),
+            text: try!(required(text, "text")),
+            debug: debug,
+            consts: consts,
+            prims: prims
+        })
+    }
 }
 
-fn read_u8<T>(file: &mut T) -> io::Result<u8> where T: Read {
-    let mut buf = [0u8];
-    try!(file.read_exact(&mut buf));
-    Ok(buf[0])
+impl gc::visit::Visitor for Library {
+    fn visit(&mut self, m: bool) {
+        self.env.visit(m);
+        self.sym_table.visit(m);
+        self.export_names.visit(m);
+        self.consts.visit(m);
+    }
 }
 
 impl Library {
-    pub fn library_path(prefix: Option<String>) -> Vec<PathBuf> {
-        let prfx = match prefix {
-            Some(s) => PathBuf::from(&s),
-            None => PathBuf::from(&DEFAULT_PREFIX)
-        };
+    // the directories searched for libraries, in order: those given by the
+    // user, those of the environment variable, the directory of the
+    // program, and the installation prefix
+    pub fn library_path(user: &[PathBuf], prog: &Path) -> Vec<PathBuf> {
+        let mut path = user.to_vec();
+
+        match env::var_os(PATH_VAR) {
+            Some(var) => path.extend(env::split_paths(&var)),
+            None => ()
+        }
 
-        vec!(prfx)
+        match prog.parent() {
+            Some(dir) if dir.as_os_str().is_empty() => path.push(PathBuf::from(".")),
+            Some(dir) => path.push(dir.to_path_buf()),
+            None => ()
+        }
+
+        path.push(PathBuf::from(DEFAULT_PREFIX));
+        path
     }
 
-    pub fn load_file(gc: &mut ::gc::GC, path: &Path, name: Box<LibName>) -> Box<Library> {
+    pub fn load_file(gc: &mut ::gc::GC, path: &Path,
+                     name: Box<LibName>) -> Result<Box<Library>, LoadError> {
         /* found library */
-        let mut f = match fs::File::open(path) {
-            Ok(f) => f,
-            Err(_) => panic!("Impossible to open library file")
-        };
+        let mut data = vec!();
+        match fs::File::open(path).and_then(|mut f| f.read_to_end(&mut data)) {
+            Ok(_) => (),
+            Err(e) => return Err(LoadError::Io(path.to_path_buf(), e))
+        }
 
-        let mut magic = [0; 3];
-        let _ = f.read(&mut magic);
+        let (mut lib, prims) = match Library::parse(gc, &data, name) {
+            Ok(res) => res,
+            Err(msg) => return Err(LoadError::Format(path.to_path_buf(), msg))
+        };
 
-        if read_u8(&mut f).unwrap() != 0x01 {
-            panic!("Unsupported file format version.");
+        for p in prims.into_iter() {
+            match primitives::lookup(&p) {
+                Some(d) => lib.prims.push(value::Primitive(d.fun, d.name)),
+                None => return Err(LoadError::UnknownPrimitive(path.to_path_buf(), p))
+            }
         }
 
-        // reserved
-        let _ = f.seek(io::SeekFrom::Current(28));
+        match lib.check_text() {
+            Ok(()) => Ok(lib),
+            Err(msg) => Err(LoadError::Format(path.to_path_buf(), msg))
+        }
+    }
 
-        let sym_tab_off = read_be_u64(&mut f).unwrap();
-        let imports_off = read_be_u64(&mut f).unwrap();
-        let exports_off = read_be_u64(&mut f).unwrap();
-        let text_off = read_be_u64(&mut f).unwrap();
+    // check the operands the VM uses as indexes without checking them
+    // when it executes the text
+    pub fn check_text(&self) -> Result<(), String> {
+        let mut off = 0;
+
+        while off < self.prog.len() {
+            let next = match disasm::instr(self, off) {
+                Some((_, next)) => next,
+                None => return Err(format!("invalid instruction at {:#06x}", off))
+            };
+
+            if self.prog[off] == bytecode::Push as u8 {
+                let ty = self.prog[off + 1];
+                let arg = self.prog[off + 2 .. next].iter().fold(0, |v, b| v << 8 | *b as u64);
+
+                let undefined = |what: &str| {
+                    Some(format!("undefined {} {} pushed at {:#06x}", what, arg, off))
+                };
+
+                let err = match ty {
+                    t if t == bytecode::Sym as u8 && arg >= self.sym_table.len() as u64 => {
+                        undefined("symbol")
+                    }
+
+                    t if t == bytecode::Const as u8 && arg >= self.consts.len() as u64 => {
+                        undefined("constant")
+                    }
+
+                    t if t == bytecode::Prim as u8 && arg >= self.prims.len() as u64 => {
+                        undefined("primitive")
+                    }
+
+                    t if t == bytecode::Char as u8 &&
+                        ::std::char::from_u32(arg as u32).is_none() => {
+                        Some(format!("invalid character {:#x} pushed at {:#06x}", arg, off))
+                    }
+
+                    _ => None
+                };
+
+                match err {
+                    Some(msg) => return Err(msg),
+                    None => ()
+                }
+            }
 
-        let _ = f.seek(io::SeekFrom::Start(imports_off));
-        let imports_count = read_be_u64(&mut f).unwrap();
+            off = next;
+        }
 
-        let mut imports = Vec::with_capacity(imports_count as usize);
-        for _ in 0 .. imports_count {
-            // read libname
-            let length = read_be_u64(&mut f).unwrap();
-            let mut lname = Vec::with_capacity(length as usize);
+        Ok(())
+    }
 
-            for _ in 0 .. length {
-                let size = read_be_u64(&mut f).unwrap();
-                let mut part = String::with_capacity(size as usize);
+    // returns the library, and the names of the primitives it uses
+    fn parse(gc: &mut ::gc::GC, file: &[u8],
+             name: Box<LibName>) -> Result<(Box<Library>, Vec<String>), String> {
+        let mut h = Reader::new(file, "header");
+
+        // the magic number wasn't fixed by version 1
+        let magic = try!(h.bytes(3));
+        let version = try!(h.u8());
+        let flags = try!(h.u8());
+        let named = flags & FLAG_NAMED != 0;
+
+        let mut sections = match version {
+            0x01 => {
+                // reserved
+                try!(h.bytes(27));
+                try!(Sections::v1(file, &mut h))
+            }
 
-                for _ in 0 .. size {
-                    let ch = read_u8(&mut f).unwrap();
-                    part.push(ch as char);
+            0x02 => {
+                if magic != MAGIC {
+                    return Err(format!("bad magic number {:?}", magic))
                 }
 
-                lname.push(part);
+                // reserved
+                try!(h.bytes(3));
+                try!(Sections::v2(file, &mut h))
             }
 
-            imports.push(Box::new(LibName(lname)));
-        }
+            v => return Err(format!("unsupported format version {:#x}", v))
+        };
 
-        let _ = f.seek(io::SeekFrom::Start(sym_tab_off));
-        let sym_count = read_be_u64(&mut f).unwrap();
-        let mut mod_symt = Vec::with_capacity(sym_count as usize);
+        // the symbol table comes first, import sets and export names
+        // refer to it
+        let r = &mut sections.symtab;
+        let sym_count = try!(r.count(8));
+        let mut mod_symt = vec!();
         debug!("{} symbols in table", sym_count);
 
         for _ in 0 .. sym_count {
-            let sz = read_be_u64(&mut f).unwrap();
-            let mut s = String::with_capacity(sz as usize);
+            let s = try!(r.string());
+            mod_symt.push(gc.intern(s));
+        }
+
+        let r = &mut sections.imports;
+        let imports_count = try!(r.count(8));
+
+        let mut imports = vec!();
+        for _ in 0 .. imports_count {
+            // read libname
+            let length = try!(r.count(8));
+            let mut lname = vec!();
 
-            for _ in 0 .. sz {
-                let b = read_u8(&mut f).unwrap();
-                s.push(b as char);
+            for _ in 0 .. length {
+                lname.push(try!(r.string()));
             }
 
-            let h = gc.intern(s);
-            mod_symt.push(h);
+            let lib = Box::new(LibName(lname));
+
+            if named {
+                let mods = try!(r.import_mods(&mod_symt));
+                let bindings = try!(r.syms(&mod_symt));
+                imports.push(Import { lib: lib, mods: mods, bindings: bindings });
+            } else {
+                imports.push(Import::positional(lib));
+            }
         }
 
-        let _ = f.seek(io::SeekFrom::Start(exports_off));
-        let exports_count = read_be_u64(&mut f).unwrap();
+        debug!("Trying to access program text section");
+        let r = &mut sections.text;
+        let text_size = try!(r.count(1));
+        let text = try!(r.bytes(text_size)).to_vec();
+
+        let r = &mut sections.exports;
+        let exports_count = try!(r.be_u64());
+
+        let export_names = if named {
+            r.pos -= 8;
+            let count = try!(r.count(8));
+            let mut names = vec!();
+
+            for _ in 0 .. count {
+                names.push(try!(r.sym(&mod_symt)));
+            }
+
+            names
+        } else {
+            // each exported value is defined by at least one
+            // instruction, this bounds the size of the environment
+            if exports_count > text.len() as u64 {
+                r.pos -= 8;
+                return Err(r.error(format!("{} exports for {} bytes of text",
+                                           exports_count, text.len())))
+            }
+
+            vec!()
+        };
+
+        let debug = match sections.debug {
+            Some(ref mut r) => Some(try!(r.debug_info(text.len()))),
+            None => None
+        };
+
+        let consts = match sections.consts {
+            Some(ref mut r) => try!(r.consts(gc, &mod_symt)),
+            None => vec!()
+        };
+
+        let prims = match sections.prims {
+            Some(ref mut r) => try!(r.strings()),
+            None => vec!()
+        };
 
         let env = gc.alloc(gc::Env {
             values: Vec::with_capacity(exports_count as usize),
             next: None
         });
 
-        debug!("Trying to access program text section at {:x}", text_off);
-        let _ = f.seek(io::SeekFrom::Start(text_off));
-        let text_size = read_be_u64(&mut f).unwrap();
-        let mut text = Vec::with_capacity(text_size as usize);
-
-        for _ in 0 .. text_size {
-            let b = read_u8(&mut f).unwrap();
-            text.push(b);
-        }
-
         debug!("Sucessfully loaded library");
-        Box::new(Library {
+        Ok((Box::new(Library {
             env: env, prog: text, name: name, sym_table: mod_symt,
-            imports: imports, exports: exports_count
-        })
+            imports: imports, exports: exports_count, export_names: export_names,
+            debug: debug, consts: consts, prims: vec!()
+        }), prims))
     }
 
-    pub fn load(gc: &mut ::gc::GC, name: &LibName, lpath: Vec<PathBuf>) -> Box<Library> {
-        let mut lpath = lpath;
+    pub fn load(gc: &mut ::gc::GC, name: &LibName,
+                lpath: &[PathBuf]) -> Result<Box<Library>, LoadError> {
+        let mut tried = vec!();
 
-        for p in lpath.iter_mut() {
-            for part in name.iter() {
-                p.push(&part);
-            }
+        for dir in lpath.iter() {
+            let p = dir.join(name.file_name());
+            debug!("Trying {}", p.display());
 
             if p.is_file() {
-                debug!("Trying {}", p.display().to_string());
-                Library::load_file(gc, &**p, Box::new(name.clone()));
+                return Library::load_file(gc, &p, Box::new(name.clone()));
             }
+
+            tried.push(p);
         }
 
-        panic!("Impossible to find library in path");
+        Err(LoadError::NotFound(name.clone(), tried))
     }
 }
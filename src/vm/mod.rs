@@ -1,6 +1,15 @@
 pub use self::exec::VM;
 pub use self::frame::Frame;
 
+pub mod error;
+pub mod image;
+pub mod interrupt;
+pub mod process;
+pub mod thread;
+
+mod debug;
+mod debuginfo;
+mod disasm;
 mod exec;
 mod frame;
 mod library;
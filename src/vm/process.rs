@@ -0,0 +1,240 @@
+use std::collections::HashSet;
+use std::io;
+use std::io::Write;
+use std::sync::mpsc;
+use std::thread;
+
+use gc;
+use gc::value;
+use gmp;
+use vm::VM;
+
+// Isolated processes
+// a process is a VM with its own heap, running on its own OS thread.
+// Processes don't share any object: messages are deep-copied out of the
+// heap of the sender and back into the heap of the receiver.
+
+pub enum Message {
+    Bool(bool),
+    Bytevector(Vec<u8>),
+    Char(char),
+    Fixnum(i64),
+    Flonum(f64),
+    HashTable(gc::Equiv, Vec<(Message, Message)>),
+    Ratio(gmp::mpq::Mpq),
+    // a proper or improper list, stored flat so that long lists don't
+    // need deep recursion to be copied or dropped
+    List(Vec<Message>, Box<Message>),
+    Mailbox(mpsc::Sender<Message>),
+    Null,
+    Num(gmp::mpz::Mpz),
+    String(String),
+    Symbol(String),
+    Unit,
+    Vector(Vec<Message>)
+}
+
+// a handle on the mailbox of a process, which can be sent to others
+
+pub struct Mailbox {
+    pub tx: mpsc::Sender<Message>
+}
+
+impl gc::visit::Visitor for Mailbox {
+    fn visit(&mut self, _: bool) {
+    }
+}
+
+impl Message {
+    // copy a value out of the heap. Fails with the offending
+    // value if it contains objects that can't be sent
+    pub fn from_value(v: &gc::Value) -> Result<Message, gc::Value> {
+        let mut path = HashSet::new();
+        Message::copy_out(v, &mut path)
+    }
+
+    fn copy_out(v: &gc::Value, path: &mut HashSet<usize>)
+                -> Result<Message, gc::Value> {
+        Ok(match v {
+            &value::Bool(b) => Message::Bool(b),
+            &value::Bytevector(bv) => Message::Bytevector(bv.bytes.clone()),
+            &value::Null => Message::Null,
+            &value::Char(c) => Message::Char(c),
+            &value::Fixnum(i) => Message::Fixnum(i),
+            &value::Flonum(f) => Message::Flonum(f),
+            &value::Ratio(ref q) => Message::Ratio(q.clone()),
+            &value::Num(ref n) => Message::Num(n.clone()),
+            &value::String(s) => Message::String(s.str.clone()),
+            &value::Symbol(s) => Message::Symbol(s.str.clone()),
+            &value::Unit => Message::Unit,
+            &value::Mailbox(mb) => Message::Mailbox(mb.tx.clone()),
+
+            &value::Pair(_) => {
+                // pairs of the list currently being copied, to detect
+                // circular structures
+                let mut pairs = vec!();
+                let mut items = vec!();
+                let mut cur = v.clone();
+
+                loop {
+                    cur = match cur {
+                        value::Pair(p) => {
+                            let gc::Ptr(addr) = p;
+                            if !path.insert(addr as usize) {
+                                return Err(v.clone())
+                            }
+
+                            pairs.push(addr as usize);
+                            items.push(try!(Message::copy_out(&p.car, path)));
+                            p.cdr.clone()
+                        }
+
+                        _ => break
+                    }
+                }
+
+                let tail = try!(Message::copy_out(&cur, path));
+
+                for addr in pairs.iter() {
+                    path.remove(addr);
+                }
+
+                Message::List(items, Box::new(tail))
+            }
+
+            &value::Vector(vec) => {
+                let gc::Ptr(addr) = vec;
+                if !path.insert(addr as usize) {
+                    return Err(v.clone())
+                }
+
+                let mut items = vec!();
+                for item in vec.values.iter() {
+                    items.push(try!(Message::copy_out(item, path)));
+                }
+
+                path.remove(&(addr as usize));
+                Message::Vector(items)
+            }
+
+            &value::HashTable(t) => {
+                let gc::Ptr(addr) = t;
+                if !path.insert(addr as usize) {
+                    return Err(v.clone())
+                }
+
+                let mut entries = vec!();
+                for (k, v) in t.entries().into_iter() {
+                    let k = try!(Message::copy_out(&k, path));
+                    entries.push((k, try!(Message::copy_out(&v, path))));
+                }
+
+                path.remove(&(addr as usize));
+                Message::HashTable(t.equiv, entries)
+            }
+
+            _ => return Err(v.clone())
+        })
+    }
+
+    // copy a message into the heap of the receiver
+    pub fn to_value(self, gc: &mut gc::GC) -> gc::Value {
+        match self {
+            Message::Bool(b) => value::Bool(b),
+            Message::Bytevector(bytes) => value::Bytevector(gc.alloc(gc::Bytevector {
+                bytes: bytes,
+                mutable: true
+            })),
+
+            Message::Null => value::Null,
+            Message::Char(c) => value::Char(c),
+            Message::Fixnum(i) => value::Fixnum(i),
+            Message::Flonum(f) => value::Flonum(f),
+            Message::Ratio(q) => value::Ratio(q),
+            Message::Num(n) => value::Num(n),
+            Message::String(s) => value::String(gc.alloc(gc::String {
+                str: s,
+                mutable: true
+            })),
+
+            Message::Symbol(s) => value::Symbol(gc.intern(s)),
+            Message::Unit => value::Unit,
+            Message::Mailbox(tx) => value::Mailbox(gc.alloc(Mailbox { tx: tx })),
+
+            Message::List(items, tail) => {
+                let mut ret = tail.to_value(gc);
+
+                for m in items.into_iter().rev() {
+                    let car = m.to_value(gc);
+                    ret = value::Pair(value::list::cons(&car, &ret, gc));
+                }
+
+                ret
+            }
+
+            Message::Vector(items) => {
+                let values = items.into_iter().map(|m| m.to_value(gc)).collect();
+                value::Vector(gc.alloc(gc::Vector { values: values, mutable: true }))
+            }
+
+            // keys hashed by address are copied, so they are hashed again
+            Message::HashTable(equiv, entries) => {
+                let mut t = gc.alloc(gc::HashTable::new(equiv));
+
+                for (k, v) in entries.into_iter() {
+                    let k = k.to_value(gc);
+                    let v = v.to_value(gc);
+                    t.insert(&k, &v);
+                }
+
+                value::HashTable(t)
+            }
+        }
+    }
+}
+
+impl VM {
+    // spawn a new process running the given program, and
+    // return its mailbox
+    pub fn spawn(&mut self, prog: String) -> mpsc::Sender<Message> {
+        let (tx, rx) = mpsc::channel();
+        let own = tx.clone();
+        let parent = self.own_mailbox.clone();
+
+        let max_depth = self.max_depth;
+        let max_stack = self.max_stack;
+        let max_nesting = self.max_nesting;
+        let preempt = self.preempt;
+        let trace = self.trace;
+        let lib_path = self.lib_path.clone();
+
+        thread::spawn(move || {
+            let mut vm = VM::new();
+            vm.mailbox = rx;
+            vm.own_mailbox = own;
+            vm.parent_mailbox = Some(parent);
+            vm.max_depth = max_depth;
+            vm.max_stack = max_stack;
+            vm.max_nesting = max_nesting;
+            vm.preempt = preempt;
+            vm.root = false;
+            vm.trace = trace;
+            vm.lib_path = lib_path;
+
+            let mut err = io::stderr();
+            match vm.catch(|vm| vm.run(&prog)) {
+                Ok(Ok(())) => (),
+                Ok(Err(e)) => {
+                    let _ = writeln!(err, "Cannot load process {}: {}", prog, e);
+                }
+
+                Err(obj) => {
+                    let _ = writeln!(err, "Uncaught exception in process {}: {}",
+                                     prog, obj);
+                }
+            }
+        });
+
+        tx
+    }
+}
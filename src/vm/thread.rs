@@ -0,0 +1,226 @@
+use std::collections::VecDeque;
+use std::mem;
+
+use gc;
+use gc::value;
+use vm::Frame;
+use vm::Stack;
+use vm::VM;
+
+// Green threads
+// all the threads of a VM share its heap. The frame chain and the stack
+// of the running thread live in the VM itself, the other threads keep
+// theirs in their descriptor until they are scheduled again.
+// Threads are only switched by the toplevel dispatch loop, between two
+// instructions, and never while a primitive is calling back into Scheme
+// code, since the native stack is shared. Consequently, a thread can't
+// block while running a closure called from a primitive (e.g. map).
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum State {
+    New,
+    Runnable,
+    Blocked,
+    Terminated
+}
+
+pub struct Thread {
+    pub thunk: gc::Value,
+    pub name: gc::Value,
+    pub state: State,
+
+    // saved execution context, while the thread is not running
+    pub frame: Option<Box<Frame>>,
+    pub stack: Stack,
+    pub depth: usize,
+
+    // the value returned by the thunk, or the uncaught object it raised
+    pub result: gc::Value,
+    pub failed: bool,
+    // threads blocked in thread-join! on this thread
+    pub joiners: Vec<gc::Ptr<Thread>>,
+    // an object to raise in this thread when it is resumed
+    pub pending: Option<gc::Value>
+}
+
+pub struct Mutex {
+    pub name: gc::Value,
+    pub owner: Option<gc::Ptr<Thread>>,
+    pub waiters: VecDeque<gc::Ptr<Thread>>
+}
+
+pub struct CondVar {
+    pub name: gc::Value,
+    pub waiters: VecDeque<gc::Ptr<Thread>>
+}
+
+impl Thread {
+    pub fn new(thunk: gc::Value, name: gc::Value, state: State) -> Thread {
+        Thread {
+            thunk: thunk, name: name, state: state,
+            frame: None, stack: vec!(), depth: 0,
+            result: value::Unit, failed: false,
+            joiners: vec!(), pending: None
+        }
+    }
+}
+
+impl gc::visit::Visitor for Thread {
+    fn visit(&mut self, m: bool) {
+        self.thunk.visit(m);
+        self.name.visit(m);
+        self.result.visit(m);
+        self.stack.visit(m);
+        self.joiners.visit(m);
+
+        match self.frame {
+            Some(ref mut f) => f.visit(m),
+            None => ()
+        }
+
+        match self.pending {
+            Some(ref mut v) => v.visit(m),
+            None => ()
+        }
+    }
+}
+
+impl gc::visit::Visitor for Mutex {
+    fn visit(&mut self, m: bool) {
+        self.name.visit(m);
+
+        match self.owner {
+            Some(ref mut t) => t.visit(m),
+            None => ()
+        }
+
+        for t in self.waiters.iter_mut() {
+            t.visit(m);
+        }
+    }
+}
+
+impl gc::visit::Visitor for CondVar {
+    fn visit(&mut self, m: bool) {
+        self.name.visit(m);
+
+        for t in self.waiters.iter_mut() {
+            t.visit(m);
+        }
+    }
+}
+
+impl VM {
+    pub fn start_thread(&mut self, mut t: gc::Ptr<Thread>) {
+        t.state = State::Runnable;
+        self.threads.push(t);
+        self.run_queue.push_back(t);
+    }
+
+    // block the current thread at the end of the current instruction
+    // the blocking primitive returns a dummy value, which is replaced by
+    // the one given to wake()
+    pub fn block(&mut self) {
+        if self.nested > 0 {
+            let msg = "cannot block in a procedure called \
+                       from a primitive".to_string();
+            self.error("thread", msg, value::Null);
+        }
+
+        if self.run_queue.is_empty() {
+            let msg = "all threads are blocked".to_string();
+            self.error("deadlock", msg, value::Null);
+        }
+
+        self.thread.state = State::Blocked;
+        self.switch_pending = true;
+    }
+
+    pub fn wake(&mut self, mut t: gc::Ptr<Thread>, ret: gc::Value) {
+        t.state = State::Runnable;
+        *t.stack.last_mut().unwrap() = ret;
+        self.run_queue.push_back(t);
+    }
+
+    // raise the error reported when joining a thread that terminated
+    // with an uncaught exception
+    pub fn raise_uncaught(&mut self, obj: gc::Value) -> ! {
+        let msg = "thread terminated by an uncaught exception".to_string();
+        let irritants = value::Pair(value::list::cons(&obj, &value::Null,
+                                                      &mut *self.gc));
+        self.error("uncaught-exception", msg, irritants)
+    }
+
+    // terminate the current thread, which must not be the primordial one
+    // it keeps running until the next call to schedule()
+    pub fn thread_exit(&mut self, result: gc::Value, failed: bool) {
+        let mut cur = self.thread;
+        cur.state = State::Terminated;
+        cur.result = result.clone();
+        cur.failed = failed;
+
+        for mut j in mem::replace(&mut cur.joiners, vec!()).into_iter() {
+            if failed {
+                j.pending = Some(result.clone());
+            }
+
+            self.wake(j, result.clone());
+        }
+
+        self.threads.retain(|t| *t != cur);
+    }
+
+    // switch to the next runnable thread, if any
+    pub fn schedule(&mut self) {
+        self.switch_pending = false;
+        self.ticks = 0;
+
+        let mut cur = self.thread;
+        if cur.state == State::Runnable {
+            self.run_queue.push_back(cur);
+        }
+
+        match self.run_queue.pop_front() {
+            Some(next) => if next != cur { self.switch_to(next) },
+            None => {
+                // nothing can wake up the remaining threads, report the
+                // error in the primordial thread
+                let primordial = self.primordial;
+                if cur != primordial {
+                    self.switch_to(primordial);
+                }
+
+                let msg = "all threads are blocked".to_string();
+                self.error("deadlock", msg, value::Null);
+            }
+        }
+
+        if cur.state == State::Terminated {
+            cur.frame = None;
+            cur.stack = vec!();
+        }
+    }
+
+    fn switch_to(&mut self, mut next: gc::Ptr<Thread>) {
+        let mut cur = self.thread;
+
+        let (frame, fresh) = match next.frame.take() {
+            Some(f) => (f, false),
+            // a thread starts with an empty frame, which becomes
+            // current again when its thunk returns
+            None => (Frame::new(self.frame.env, 0, 0), true)
+        };
+
+        cur.frame = Some(mem::replace(&mut self.frame, frame));
+        cur.stack = mem::replace(&mut self.stack,
+                                 mem::replace(&mut next.stack, vec!()));
+        cur.depth = self.depth;
+        self.depth = next.depth;
+        self.thread = next;
+
+        if fresh {
+            let thunk = next.thunk.clone();
+            self.fun_call(&thunk, 0);
+        }
+    }
+}